@@ -0,0 +1,106 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Stand-ins for the parts of `cortex_m0` the shell uses, built on std, so the shell can be built
+// and tested on the host with `cargo test`. Only the shell core is built for the host, anything
+// that needs tasks or the board's hardware is left out.
+
+pub mod kernel {
+    pub mod collections {
+        pub use std::vec::Vec;
+        pub use std::string::String;
+    }
+
+    pub mod alloc {
+        pub use std::boxed::Box;
+    }
+
+    pub mod sync {
+        use core::cell::UnsafeCell;
+        use core::ops::{Deref, DerefMut};
+        use core::sync::atomic::{AtomicBool, Ordering};
+
+        /// A spin lock with the same interface as the kernel's `Mutex`, so it can be used in
+        /// statics.
+        pub struct Mutex<T> {
+            locked: AtomicBool,
+            data: UnsafeCell<T>,
+        }
+
+        unsafe impl<T: Send> Sync for Mutex<T> {}
+
+        impl<T> Mutex<T> {
+            pub const fn new(data: T) -> Self {
+                Mutex {
+                    locked: AtomicBool::new(false),
+                    data: UnsafeCell::new(data),
+                }
+            }
+
+            pub fn lock(&self) -> MutexGuard<T> {
+                while self.locked.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {}
+                MutexGuard { mutex: self }
+            }
+        }
+
+        pub struct MutexGuard<'a, T: 'a> {
+            mutex: &'a Mutex<T>,
+        }
+
+        impl<'a, T> Deref for MutexGuard<'a, T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                unsafe { &*self.mutex.data.get() }
+            }
+        }
+
+        impl<'a, T> DerefMut for MutexGuard<'a, T> {
+            fn deref_mut(&mut self) -> &mut T {
+                unsafe { &mut *self.mutex.data.get() }
+            }
+        }
+
+        impl<'a, T> Drop for MutexGuard<'a, T> {
+            fn drop(&mut self) {
+                self.mutex.locked.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+pub mod time {
+    use std::thread;
+    use std::time::Duration;
+
+    pub struct Time {
+        pub sec: usize,
+        pub ms: usize,
+    }
+
+    /// There is no system tick on the host, so the time is always zero.
+    pub fn now() -> Time {
+        Time {
+            sec: 0,
+            ms: 0,
+        }
+    }
+
+    pub fn delay_ms(ms: usize) {
+        thread::sleep(Duration::from_millis(ms as u64));
+    }
+}
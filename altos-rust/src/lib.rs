@@ -17,32 +17,59 @@
 
 // This module is the entry point to the operating system.
 // Application tasks are created here.
+//
+// `cortex_m0` is only available when building for the board. On any other target only the shell
+// core is built, against the stand-ins in `host`, so that it can be tested with `cargo test`.
 
 #![feature(const_fn)]
+#![cfg_attr(target_arch = "arm", feature(asm))]
 #![no_std]
 #![allow(dead_code)]
 
 #![allow(unused_imports)]
+#[cfg(target_arch = "arm")]
 #[macro_use]
 extern crate cortex_m0;
+#[cfg(not(target_arch = "arm"))]
+#[macro_use]
+extern crate std;
 
+#[cfg(target_arch = "arm")]
 use cortex_m0::arm;
+#[cfg(target_arch = "arm")]
 use cortex_m0::kernel;
+#[cfg(target_arch = "arm")]
 use cortex_m0::time;
+#[cfg(target_arch = "arm")]
 use cortex_m0::kernel::task::Priority;
+#[cfg(target_arch = "arm")]
 use cortex_m0::kernel::task::args::Args;
+#[cfg(target_arch = "arm")]
 use cortex_m0::kernel::sync::{Mutex, RawMutex};
+#[cfg(target_arch = "arm")]
 use cortex_m0::peripheral::gpio::{self, Port};
+#[cfg(target_arch = "arm")]
 use cortex_m0::io;
 
+#[cfg(not(target_arch = "arm"))]
+use host::{kernel, time};
+
 mod shell;
+#[cfg(target_arch = "arm")]
+mod tasks;
+#[cfg(not(target_arch = "arm"))]
+mod host;
 
+#[cfg(target_arch = "arm")]
 #[no_mangle]
 pub fn application_entry() -> ! {
     // -----------------
     // Tasks go between the lines.
+    // Shell commands and spawnable tasks can be added with `shell::register` and
+    // `shell::register_task`, and pins used by tasks claimed with `shell::claim_pin`, before the
+    // scheduler starts.
     // ----------------
-    kernel::syscall::new_task(shell::shell, Args::empty(), 2048, Priority::Normal, "shell");
+    tasks::new_task(shell::shell, Args::empty(), 2048, Priority::Normal, "shell");
     kernel::task::start_scheduler();
 
     loop { unsafe { arm::asm::bkpt() }; }
@@ -0,0 +1,24 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Aliases set with `alias`. When the first word of a command is an alias it is replaced by the
+// words of the alias before the command is looked up.
+
+use kernel::collections::String;
+use super::table::Table;
+
+pub type Aliases = Table<String>;
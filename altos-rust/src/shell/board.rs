@@ -0,0 +1,344 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The builtin commands that need the kernel's tasks or the board's hardware. These are only
+// available when the shell is built for the board.
+
+use cortex_m0::kernel;
+use cortex_m0::time::delay_ms;
+use kernel::task::{State, Priority};
+use kernel::task::args::{ArgsBuilder, Args};
+use kernel::collections::{Vec, String};
+use core::fmt::{self, Write};
+use tasks::{self, TaskInfo};
+use super::Shell;
+use super::command::{Command, Status, SUCCESS, FAILURE, USAGE, INTERRUPTED};
+use super::builtins::KEY_POLL_MS;
+use super::gpio::{self, GPIO_USAGE};
+use super::memory::{self, PEEK_USAGE, POKE_USAGE, HEXDUMP_USAGE};
+use super::jobs;
+use super::line::CTRL_C;
+use super::spawn;
+
+const BLINK_HELP: &'static str = "Blink the LED at the given rate in milliseconds in the background, until stopped with stop";
+const STOP_HELP: &'static str = "Stop any blink tasks, whether started with blink or spawn, and turn off the LED";
+const PS_HELP: &'static str = "List running tasks with their priority, state, stack size and the most stack they have used";
+const SPAWN_HELP: &'static str = "Start a task, optionally overriding its priority (critical, normal or low) and stack size";
+const KILL_HELP: &'static str = "Destroy a task by name or id, or a background job by %N";
+const JOBS_HELP: &'static str = "List the commands running in the background";
+const FG_HELP: &'static str = "Wait for a background job to finish, the last one started by default. Ctrl-C kills it";
+const GPIO_HELP: &'static str = "Read a pin, drive it high or low, or set its mode and pull. `gpio list` shows the pins in use";
+const PEEK_HELP: &'static str = "Read memory or a register, count values of the given width in bits (32 by default)";
+const POKE_HELP: &'static str = "Write a value to memory or a register";
+const HEXDUMP_HELP: &'static str = "Dump memory as hex and ASCII like xxd, from an address or one of the .data, .bss, heap or stack regions";
+const EXIT_HELP: &'static str = "Exit the shell";
+
+/// The stack size of the blink task, whether started with `blink` or `spawn`.
+pub const BLINK_STACK_SIZE: usize = 1024;
+
+const SPAWN_USAGE: &'static str = "spawn <name> [args ...] [--prio P] [--stack N]";
+const KILL_USAGE: &'static str = "kill <name|id|%job>";
+
+pub static BOARD_BUILTINS: [Command; 12] = [
+    Command { name: "blink", usage: "blink [rate]", help: BLINK_HELP, handler: blink, complete: None },
+    Command { name: "stop", usage: "stop", help: STOP_HELP, handler: stop, complete: None },
+    Command { name: "gpio", usage: GPIO_USAGE, help: GPIO_HELP, handler: gpio::gpio, complete: Some(gpio::complete_gpio) },
+    Command { name: "peek", usage: PEEK_USAGE, help: PEEK_HELP, handler: memory::peek, complete: None },
+    Command { name: "poke", usage: POKE_USAGE, help: POKE_HELP, handler: memory::poke, complete: None },
+    Command { name: "hexdump", usage: HEXDUMP_USAGE, help: HEXDUMP_HELP, handler: memory::hexdump, complete: None },
+    Command { name: "ps", usage: "ps", help: PS_HELP, handler: ps, complete: None },
+    Command { name: "spawn", usage: SPAWN_USAGE, help: SPAWN_HELP, handler: spawn, complete: Some(complete_spawn) },
+    Command { name: "kill", usage: KILL_USAGE, help: KILL_HELP, handler: kill, complete: Some(complete_kill) },
+    Command { name: "jobs", usage: "jobs", help: JOBS_HELP, handler: jobs, complete: None },
+    Command { name: "fg", usage: "fg [%job]", help: FG_HELP, handler: fg, complete: None },
+    Command { name: "exit", usage: "exit", help: EXIT_HELP, handler: exit, complete: None },
+];
+
+fn blink(shell: &mut Shell, args: &[&str]) -> Status {
+    let rate: usize = if args.len() > 0 {
+        args[0].parse::<usize>().unwrap_or(100)
+    }
+    else {
+        100
+    };
+
+    stop_blinking(shell);
+    let mut task_args = ArgsBuilder::with_capacity(1);
+    task_args.add_num(rate);
+    tasks::new_task(blink_task, task_args.finalize(), BLINK_STACK_SIZE, Priority::Low, "blink");
+    SUCCESS
+}
+
+fn stop(shell: &mut Shell, _args: &[&str]) -> Status {
+    stop_blinking(shell);
+    SUCCESS
+}
+
+// Destroy any blink tasks and turn off the LED.
+fn stop_blinking(shell: &mut Shell) {
+    // Blink can be started with `spawn` as well as `blink`, so go by the running tasks rather
+    // than the jobs. Job tasks are named after their command.
+    for task in tasks::tasks().iter().filter(|task| task.name == "blink") {
+        let mut handle = task.handle;
+        handle.destroy();
+    }
+    let blinking: Vec<usize> = shell.jobs.iter()
+        .filter(|job| job.name == "blink")
+        .map(|job| job.id)
+        .collect();
+    for id in blinking {
+        shell.jobs.remove(id);
+    }
+    turn_off_led();
+}
+
+fn ps(shell: &mut Shell, _args: &[&str]) -> Status {
+    let mut tasks = tasks::tasks();
+    tasks.sort_by_key(|task| task.handle.tid().unwrap_or(0));
+    cprintln!(shell.console, "{:>4}  {:<12}{:<10}{:<11}{:>6}{:>6}", "ID", "NAME", "PRIORITY", "STATE", "STACK", "USED");
+    for task in tasks {
+        // The task may have exited since the list was taken.
+        let (tid, state) = match (task.handle.tid(), task.handle.state()) {
+            (Ok(tid), Ok(state)) => (tid, state),
+            _ => continue,
+        };
+        let used = match task.stack_used() {
+            Some(used) => debug_string(&used),
+            None => String::from("-"),
+        };
+        cprintln!(shell.console, "{:>4}  {:<12}{:<10}{:<11}{:>6}{:>6}",
+            tid, task.name, debug_string(&task.priority), debug_string(&state), task.stack_size, used);
+    }
+    let untracked = tasks::untracked();
+    if untracked > 0 {
+        cprintln!(shell.console, "{} more task(s) started after the task list was full can't be listed or killed by name",
+            untracked);
+    }
+    SUCCESS
+}
+
+fn spawn(shell: &mut Shell, args: &[&str]) -> Status {
+    let (name, args) = match args.split_first() {
+        Some((name, args)) => (*name, args),
+        None => {
+            cprintln!(shell.console, "Usage: {}", SPAWN_USAGE);
+            return USAGE;
+        },
+    };
+    let task = match spawn::find(name) {
+        Some(task) => task,
+        None => {
+            cprintln!(shell.console, "Unknown task: '{}'", name);
+            return FAILURE;
+        },
+    };
+
+    let mut priority = task.priority;
+    let mut stack_size = task.stack_size;
+    let mut nums = Vec::new();
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "--prio" => match args.next().and_then(|prio| spawn::parse_priority(prio)) {
+                Some(prio) => priority = prio,
+                None => {
+                    cprintln!(shell.console, "--prio expects one of critical, normal or low");
+                    return USAGE;
+                },
+            },
+            "--stack" => match args.next().and_then(|size| size.parse::<usize>().ok()) {
+                // The kernel builds the task's first frame at the top of the stack, so a stack that
+                // is too small or unaligned corrupts the heap. No one task gets more than half of it.
+                Some(size) if size >= tasks::MIN_STACK_SIZE && size <= memory::heap_size() / 2 && size % 4 == 0 => {
+                    stack_size = size;
+                },
+                _ => {
+                    cprintln!(shell.console, "--stack expects a multiple of 4 bytes from {} to {}",
+                        tasks::MIN_STACK_SIZE, memory::heap_size() / 2);
+                    return USAGE;
+                },
+            },
+            num => match num.parse::<usize>() {
+                Ok(num) => nums.push(num),
+                Err(_) => {
+                    cprintln!(shell.console, "Invalid argument: '{}'", num);
+                    return USAGE;
+                },
+            },
+        }
+    }
+
+    let mut task_args = ArgsBuilder::with_capacity(nums.len());
+    for num in nums {
+        task_args.add_num(num);
+    }
+    let handle = tasks::new_task(task.code, task_args.finalize(), stack_size, priority, task.name);
+    if let Ok(tid) = handle.tid() {
+        cprintln!(shell.console, "Started {} with id {}", task.name, tid);
+    }
+    SUCCESS
+}
+
+fn kill(shell: &mut Shell, args: &[&str]) -> Status {
+    let target = match args.first() {
+        Some(target) => *target,
+        None => {
+            cprintln!(shell.console, "Usage: {}", KILL_USAGE);
+            return USAGE;
+        },
+    };
+    if target.starts_with('%') {
+        return kill_job(shell, target);
+    }
+    let matches: Vec<TaskInfo> = tasks::tasks().into_iter()
+        .filter(|task| match target.parse::<usize>() {
+            Ok(tid) => task.handle.tid() == Ok(tid),
+            Err(_) => task.name == target,
+        })
+        .collect();
+
+    match matches.len() {
+        0 => cprintln!(shell.console, "No such task: '{}'", target),
+        1 => {
+            let mut handle = matches[0].handle;
+            // The only task running while a command executes is the shell itself.
+            if let Ok(State::Running) = handle.state() {
+                cprintln!(shell.console, "Can't kill the shell, use exit instead");
+                return FAILURE;
+            }
+            handle.destroy();
+            return SUCCESS;
+        },
+        _ => cprintln!(shell.console, "More than one task named '{}', kill it by id instead", target),
+    }
+    FAILURE
+}
+
+fn kill_job(shell: &mut Shell, target: &str) -> Status {
+    let job = jobs::parse_id(target).and_then(|id| shell.jobs.remove(id));
+    match job {
+        Some(mut job) => {
+            job.handle.destroy();
+            SUCCESS
+        },
+        None => {
+            cprintln!(shell.console, "No such job: '{}'", target);
+            FAILURE
+        },
+    }
+}
+
+fn jobs(shell: &mut Shell, _args: &[&str]) -> Status {
+    for job in shell.jobs.iter() {
+        let state = if job.is_alive() { "Running" } else { "Done" };
+        cprintln!(shell.console, "[{}] {:<8} {}", job.id, state, job.command);
+    }
+    shell.jobs.remove_finished();
+    SUCCESS
+}
+
+fn fg(shell: &mut Shell, args: &[&str]) -> Status {
+    let id = match args.first() {
+        Some(arg) => match jobs::parse_id(arg) {
+            Some(id) => id,
+            None => {
+                cprintln!(shell.console, "Usage: fg [%job]");
+                return USAGE;
+            },
+        },
+        None => match shell.jobs.last() {
+            Some(job) => job.id,
+            None => {
+                cprintln!(shell.console, "No jobs running");
+                return FAILURE;
+            },
+        },
+    };
+    let mut handle = match shell.jobs.get(id) {
+        Some(job) => {
+            cprintln!(shell.console, "{}", job.command);
+            job.handle
+        },
+        None => {
+            cprintln!(shell.console, "No such job: '{}'", id);
+            return FAILURE;
+        },
+    };
+
+    loop {
+        if handle.tid().is_err() {
+            shell.jobs.remove(id);
+            return SUCCESS;
+        }
+        if shell.console.read_char() == Some(CTRL_C) {
+            cprintln!(shell.console, "^C");
+            handle.destroy();
+            shell.jobs.remove(id);
+            return INTERRUPTED;
+        }
+        delay_ms(KEY_POLL_MS);
+    }
+}
+
+fn exit(_shell: &mut Shell, _args: &[&str]) -> Status {
+    kernel::syscall::exit();
+}
+
+fn complete_spawn(_shell: &Shell, args: &[&str]) -> Vec<&'static str> {
+    if args.len() == 0 {
+        spawn::spawnable().iter().map(|task| task.name).collect()
+    }
+    else {
+        Vec::new()
+    }
+}
+
+fn complete_kill(_shell: &Shell, args: &[&str]) -> Vec<&'static str> {
+    if args.len() == 0 {
+        tasks::tasks().iter().map(|task| task.name).collect()
+    }
+    else {
+        Vec::new()
+    }
+}
+
+// Derived `Debug` implementations ignore the width, so format into a string first to pad columns.
+fn debug_string<T: fmt::Debug>(value: &T) -> String {
+    let mut string = String::new();
+    let _ = write!(string, "{:?}", value);
+    string
+}
+
+fn turn_on_led() {
+    let mut led = gpio::LED.enabled_port();
+    led.set();
+}
+
+fn turn_off_led() {
+    let mut led = gpio::LED.enabled_port();
+    led.reset();
+}
+
+pub fn blink_task(args: &mut Args) {
+    let rate = args.pop_num();
+    loop {
+        turn_on_led();
+        delay_ms(rate);
+        turn_off_led();
+        delay_ms(rate);
+    }
+}
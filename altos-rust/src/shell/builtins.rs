@@ -0,0 +1,575 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The commands that are always available in the shell. Commands that need the kernel's tasks or
+// the board's hardware are in `board`.
+
+use time::{delay_ms, now};
+use kernel::collections::{Vec, String};
+use core::cmp;
+use super::Shell;
+use super::command::{self, Command, Status, SUCCESS, FAILURE, USAGE, INTERRUPTED};
+use super::eval::{self, Lexer, Parser, Statement};
+use super::env;
+use super::line::{self, Interrupt, CTRL_C};
+use super::script;
+
+/*
+const LOGO: &'static str = "
+            .
+            ;'                ..      '.
+           .cc.              x0kk.   .Oo  .xc
+           c,;:            .OO. xO.  .Oo 'dkxoc .d.  .o' .clccc.
+          ;'  ;'           kKdodd0k. .Oo  .kl   ,k,  'x; ,dc;,'.
+         ..    .          cXl   .o0o .Oo  .ko   ,k;  ;x;    ..ld.
+         c:.  .::         oO.     dx  xc   cddo, :dolcd' ;lcclc,
+        ;c;,',':c'
+       .cc,    :cc.
+      .ccl;    cll:        'dl   ;o;            ..
+      :odxc    dxdd;       kK0d cOkO    .''.    ok    ...    .    .   .....  ...
+     ,xkO0o    k00Ox.     .Kx.k0O':O, 'x'  'k:lookol.oxcx;. ;d.  ,d' ,oc.'ll:.'l:
+    .k0KKKd    OXKK0k     ,Xl  l   O: xOoccccc  ck.  ox  '' ;d.  ,d' ,o.  :l.  :l
+    d0KKXXd    OXXXKKl    ;Xl      Oc 'kd;      :k   ox     'dc  'd' ,o.  :l.  :l
+  ;x0KXXO;      cKXXKKx,  xl.      0c.  lcccc   :l   ox      'cccc'   0   :l.  :l
+.lxOKKXo          kXKK0ko.                                                          ";
+*/
+
+const ECHO_HELP: &'static str = "Echo a string to the terminal";
+const CLEAR_HELP: &'static str = "Clear the terminal";
+const EVAL_HELP: &'static str = "Evaluate an integer expression using + - * / % & | ^ ~ << >> and parentheses, numbers may be given in hex (0x) or binary (0b). Assign the result to a variable with `eval x = <expr>`";
+const VARS_HELP: &'static str = "List the variables assigned with eval";
+const SET_HELP: &'static str = "Set an environment variable, which can be used in commands as $NAME or ${NAME}";
+const UNSET_HELP: &'static str = "Remove an environment variable";
+const ENV_HELP: &'static str = "List the environment variables";
+const UPTIME_HELP: &'static str = "Display how long the system has been running as HH:MM:SS";
+const SCRIPT_HELP: &'static str = "Type in a new script, one command per line ending with a line containing only '.', or list, show or delete stored scripts";
+const RUN_HELP: &'static str = "Run each line of a stored script, returning the status of the last line. Ctrl-C stops it";
+const ALIAS_HELP: &'static str = "Set an alias that replaces a command name with other words, or list the aliases";
+const UNALIAS_HELP: &'static str = "Remove an alias";
+const WATCH_HELP: &'static str = "Clear the screen and run a command every N milliseconds (1000 by default) until a key is pressed";
+const STATUS_HELP: &'static str = "Display the exit status of the last command, also available as $?. `status prompt on` shows a non-zero status in the prompt";
+
+const SCRIPT_USAGE: &'static str = "script new|show|delete <name> | script list";
+const WATCH_USAGE: &'static str = "watch [-n ms] <cmd> [args ...]";
+const STATUS_USAGE: &'static str = "status [prompt on|off]";
+//const ROCKET_HELP: &'static str = "Deploys a rocket?";
+const UNAME_HELP: &'static str = "Displays system information";
+const HELP_HELP: &'static str = "Display available commands or more information about a certain command";
+
+const SCRIPT_PROMPT: &'static str = " | ";
+
+/// How often to check for a key press while waiting.
+pub const KEY_POLL_MS: usize = 10;
+
+pub static BUILTINS: [Command; 16] = [
+    Command { name: "echo", usage: "echo [string ...]", help: ECHO_HELP, handler: echo, complete: None },
+    Command { name: "clear", usage: "clear", help: CLEAR_HELP, handler: clear, complete: None },
+    Command { name: "eval", usage: "eval [name =] <expr>", help: EVAL_HELP, handler: eval, complete: None },
+    Command { name: "vars", usage: "vars", help: VARS_HELP, handler: vars, complete: None },
+    Command { name: "set", usage: "set <name> [value ...]", help: SET_HELP, handler: set, complete: None },
+    Command { name: "unset", usage: "unset <name>", help: UNSET_HELP, handler: unset, complete: None },
+    Command { name: "env", usage: "env", help: ENV_HELP, handler: env, complete: None },
+    Command { name: "script", usage: SCRIPT_USAGE, help: SCRIPT_HELP, handler: script, complete: None },
+    Command { name: "run", usage: "run <script>", help: RUN_HELP, handler: run, complete: None },
+    Command { name: "alias", usage: "alias [name[=value] ...]", help: ALIAS_HELP, handler: alias, complete: None },
+    Command { name: "unalias", usage: "unalias <name>", help: UNALIAS_HELP, handler: unalias, complete: None },
+    Command { name: "watch", usage: WATCH_USAGE, help: WATCH_HELP, handler: watch, complete: None },
+    Command { name: "status", usage: STATUS_USAGE, help: STATUS_HELP, handler: status, complete: None },
+    Command { name: "uptime", usage: "uptime", help: UPTIME_HELP, handler: uptime, complete: None },
+    //Command { name: "rocket", usage: "rocket [timer]", help: ROCKET_HELP, handler: rocket, complete: None },
+    Command { name: "uname", usage: "uname", help: UNAME_HELP, handler: uname, complete: None },
+    Command { name: "help", usage: "help [cmd]", help: HELP_HELP, handler: help, complete: Some(complete_help) },
+];
+
+fn echo(shell: &mut Shell, args: &[&str]) -> Status {
+    for word in args {
+        cprint!(shell.console, "{} ", word);
+    }
+    cprintln!(shell.console);
+    SUCCESS
+}
+
+fn clear(shell: &mut Shell, _args: &[&str]) -> Status {
+    // ANSI ESC sequence to clear screen and put cursor at at top of terminal.
+    cprint!(shell.console, "\x1b[2J");
+    SUCCESS
+}
+
+fn eval(shell: &mut Shell, args: &[&str]) -> Status {
+    let mut source = String::new();
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            source.push(' ');
+        }
+        source.push_str(arg);
+    }
+
+    let tokens = match Lexer::lex(&source) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            show_column(shell, &source, err.column());
+            cprintln!(shell.console, "Lexing failed: {}", err);
+            return FAILURE;
+        },
+    };
+    let mut parser = Parser::new(tokens);
+    match parser.parse() {
+        Ok(Statement::Assign(name, expr)) => match expr.eval(&shell.variables) {
+            Ok(result) => {
+                shell.variables.set(name, result);
+                cprintln!(shell.console, "{} = {} ({:#x})", name, result, result);
+                SUCCESS
+            },
+            Err(err) => {
+                cprintln!(shell.console, "Evaluation failed: {}", err);
+                FAILURE
+            },
+        },
+        Ok(Statement::Expr(expr)) => match expr.eval(&shell.variables) {
+            Ok(result) => {
+                cprintln!(shell.console, "Result: {} ({:#x})", result, result);
+                SUCCESS
+            },
+            Err(err) => {
+                cprintln!(shell.console, "Evaluation failed: {}", err);
+                FAILURE
+            },
+        },
+        Err(err) => {
+            show_column(shell, &source, err.column());
+            cprintln!(shell.console, "Parsing failed: {}", err);
+            FAILURE
+        },
+    }
+}
+
+// Print the expression with a caret under the character at `column`.
+fn show_column(shell: &mut Shell, source: &str, column: usize) {
+    cprintln!(shell.console, "    {}", source);
+    cprint!(shell.console, "    ");
+    for _ in 0..column {
+        cprint!(shell.console, " ");
+    }
+    cprintln!(shell.console, "^");
+}
+
+fn vars(shell: &mut Shell, _args: &[&str]) -> Status {
+    for &(ref name, value) in shell.variables.iter() {
+        cprintln!(shell.console, "{} = {} ({:#x})", name, value, value);
+    }
+    SUCCESS
+}
+
+fn set(shell: &mut Shell, args: &[&str]) -> Status {
+    let (name, words) = match args.split_first() {
+        Some((name, words)) => (*name, words),
+        None => {
+            cprintln!(shell.console, "Usage: set <name> [value ...]");
+            return USAGE;
+        },
+    };
+    let mut value = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            value.push(' ');
+        }
+        value.push_str(word);
+    }
+    match shell.env.set(name, &value) {
+        Ok(()) => SUCCESS,
+        Err(err) => {
+            cprintln!(shell.console, "Can't set {}: {}", name, err);
+            FAILURE
+        },
+    }
+}
+
+fn unset(shell: &mut Shell, args: &[&str]) -> Status {
+    let mut status = SUCCESS;
+    for name in args {
+        if let Err(err) = shell.env.unset(name) {
+            cprintln!(shell.console, "Can't unset {}: {}", name, err);
+            status = FAILURE;
+        }
+    }
+    status
+}
+
+fn env(shell: &mut Shell, _args: &[&str]) -> Status {
+    for name in &env::READ_ONLY {
+        if let Some(value) = shell.variable(name) {
+            cprintln!(shell.console, "{}={}", name, value);
+        }
+    }
+    for &(ref name, ref value) in shell.env.iter() {
+        cprintln!(shell.console, "{}={}", name, value);
+    }
+    SUCCESS
+}
+
+fn script(shell: &mut Shell, args: &[&str]) -> Status {
+    match (args.get(0).cloned(), args.get(1).cloned(), args.len()) {
+        (Some("new"), Some(name), 2) => new_script(shell, name),
+        (Some("list"), None, 1) => {
+            for &(ref name, ref lines) in shell.scripts.iter() {
+                cprintln!(shell.console, "{} ({} lines)", name, lines.len());
+            }
+            SUCCESS
+        },
+        (Some("show"), Some(name), 2) => match shell.scripts.get(name) {
+            Some(lines) => {
+                for line in lines {
+                    cprintln!(shell.console, "{}", line);
+                }
+                SUCCESS
+            },
+            None => {
+                cprintln!(shell.console, "No such script: '{}'", name);
+                FAILURE
+            },
+        },
+        (Some("delete"), Some(name), 2) => {
+            if shell.scripts.remove(name) {
+                SUCCESS
+            }
+            else {
+                cprintln!(shell.console, "No such script: '{}'", name);
+                FAILURE
+            }
+        },
+        _ => {
+            cprintln!(shell.console, "Usage: {}", SCRIPT_USAGE);
+            USAGE
+        },
+    }
+}
+
+fn new_script(shell: &mut Shell, name: &str) -> Status {
+    if !eval::is_identifier(name) {
+        cprintln!(shell.console, "Invalid script name: '{}'", name);
+        return USAGE;
+    }
+    cprintln!(shell.console, "Enter one command per line, end with a line containing only '.'");
+    let mut lines = Vec::new();
+    loop {
+        let line = match line::edit_line(shell, SCRIPT_PROMPT) {
+            Ok(line) => line,
+            Err(Interrupt::Cancel) => return INTERRUPTED,
+            Err(Interrupt::EndOfInput) => break,
+        };
+        if line.trim() == "." {
+            break;
+        }
+        lines.push(line);
+    }
+    shell.scripts.set(name, lines);
+    SUCCESS
+}
+
+fn run(shell: &mut Shell, args: &[&str]) -> Status {
+    let name = match args.first() {
+        Some(name) => *name,
+        None => {
+            cprintln!(shell.console, "Usage: run <script>");
+            return USAGE;
+        },
+    };
+    let lines = match shell.scripts.get(name) {
+        Some(lines) => lines.clone(),
+        None => {
+            cprintln!(shell.console, "No such script: '{}'", name);
+            return FAILURE;
+        },
+    };
+    if shell.run_depth == script::MAX_DEPTH {
+        cprintln!(shell.console, "Can't run '{}', scripts are nested too deeply", name);
+        return FAILURE;
+    }
+
+    shell.run_depth += 1;
+    let mut status = SUCCESS;
+    for (i, line) in lines.iter().enumerate() {
+        // Ctrl-C between lines stops the script too. Other keys typed while it runs are dropped.
+        if i > 0 && shell.console.read_char() == Some(CTRL_C) {
+            cprintln!(shell.console, "^C");
+            status = INTERRUPTED;
+            break;
+        }
+        status = shell.run_line(line);
+        if status == INTERRUPTED {
+            break;
+        }
+    }
+    shell.run_depth -= 1;
+    status
+}
+
+fn alias(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() == 0 {
+        for &(ref name, ref value) in shell.aliases.iter() {
+            cprintln!(shell.console, "alias {}='{}'", name, value);
+        }
+        return SUCCESS;
+    }
+
+    let mut status = SUCCESS;
+    for arg in args {
+        match arg.find('=') {
+            Some(index) => {
+                let (name, value) = (&arg[..index], &arg[index + 1..]);
+                if eval::is_identifier(name) {
+                    shell.aliases.set(name, String::from(value));
+                }
+                else {
+                    cprintln!(shell.console, "Invalid alias name: '{}'", name);
+                    status = USAGE;
+                }
+            },
+            None => match shell.aliases.get(arg) {
+                Some(value) => cprintln!(shell.console, "alias {}='{}'", arg, value),
+                None => {
+                    cprintln!(shell.console, "No such alias: '{}'", arg);
+                    status = FAILURE;
+                },
+            },
+        }
+    }
+    status
+}
+
+fn unalias(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() == 0 {
+        cprintln!(shell.console, "Usage: unalias <name>");
+        return USAGE;
+    }
+    let mut status = SUCCESS;
+    for name in args {
+        if !shell.aliases.remove(name) {
+            cprintln!(shell.console, "No such alias: '{}'", name);
+            status = FAILURE;
+        }
+    }
+    status
+}
+
+fn watch(shell: &mut Shell, args: &[&str]) -> Status {
+    let (interval, command) = if args.first() == Some(&"-n") {
+        match args.get(1).and_then(|ms| ms.parse::<usize>().ok()) {
+            Some(ms) if ms > 0 => (ms, &args[2..]),
+            _ => {
+                cprintln!(shell.console, "-n expects a number of milliseconds");
+                return USAGE;
+            },
+        }
+    }
+    else {
+        (1000, args)
+    };
+    if command.len() == 0 {
+        cprintln!(shell.console, "Usage: {}", WATCH_USAGE);
+        return USAGE;
+    }
+
+    let words: Vec<String> = command.iter().map(|word| String::from(*word)).collect();
+    loop {
+        cprint!(shell.console, "\x1b[2J");
+        cprintln!(shell.console, "Every {}ms, press any key to stop", interval);
+        let status = shell.run_words(&words);
+        match wait_for_key(shell, interval) {
+            Some(CTRL_C) => return INTERRUPTED,
+            Some(_) => return status,
+            None => {},
+        }
+    }
+}
+
+/// Wait for `ms` milliseconds, returning early with the key if one is pressed.
+///
+/// A closed console is taken as Ctrl-C, so nothing waits on it forever.
+pub fn wait_for_key(shell: &mut Shell, ms: usize) -> Option<char> {
+    let mut waited = 0;
+    loop {
+        if let Some(key) = shell.console.read_char() {
+            return Some(key);
+        }
+        if shell.console.is_closed() {
+            return Some(CTRL_C);
+        }
+        if waited >= ms {
+            return None;
+        }
+        let step = cmp::min(KEY_POLL_MS, ms - waited);
+        delay_ms(step);
+        waited += step;
+    }
+}
+
+fn status(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() == 0 {
+        cprintln!(shell.console, "{}", shell.status);
+        return SUCCESS;
+    }
+    match (args[0], args.get(1).cloned()) {
+        ("prompt", Some("on")) => shell.show_status = true,
+        ("prompt", Some("off")) => shell.show_status = false,
+        _ => {
+            cprintln!(shell.console, "Usage: {}", STATUS_USAGE);
+            return USAGE;
+        },
+    }
+    SUCCESS
+}
+
+fn uptime(shell: &mut Shell, _args: &[&str]) -> Status {
+    let hms = get_uptime();
+    cprintln!(shell.console, "{:02}:{:02}:{:02}", hms.0, hms.1, hms.2);
+    SUCCESS
+}
+
+/*
+fn rocket(_shell: &mut Shell, args: &[&str]) -> Status {
+    let timer = if args.len() > 0 {
+        args[0].parse::<isize>().unwrap_or(5)
+    }
+    else {
+        5
+    };
+    launch_rocket(timer);
+    SUCCESS
+}
+*/
+
+fn uname(shell: &mut Shell, _args: &[&str]) -> Status {
+    //println!("{}\n", LOGO);
+    //Find more info and place it here
+    cprintln!(shell.console, "AltOS Rust");
+    SUCCESS
+}
+
+fn help(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() > 0 {
+        match command::find(args[0]) {
+            Some(command) => cprintln!(shell.console, "{}\n    {}", command.usage, command.help),
+            None => {
+                cprintln!(shell.console, "Unknown command: {}", args[0]);
+                return FAILURE;
+            },
+        }
+    }
+    else {
+        cprintln!(shell.console, "Available Commands:");
+        for command in command::commands() {
+            cprintln!(shell.console, "    {}", command.usage);
+        }
+    }
+    SUCCESS
+}
+
+fn complete_help(_shell: &Shell, args: &[&str]) -> Vec<&'static str> {
+    if args.len() == 0 {
+        command::commands().iter().map(|command| command.name).collect()
+    }
+    else {
+        Vec::new()
+    }
+}
+
+pub fn get_uptime() -> (usize, usize, usize) {
+    let curr_time = now();
+
+    let mut minutes = curr_time.sec / 60;
+    let hours = minutes / 60;
+    let seconds = curr_time.sec % 60;
+    minutes = minutes % 60;
+
+    (hours, minutes, seconds)
+}
+
+/*
+fn launch_rocket(mut timer: isize) {
+    let mut offset: isize = 15;
+    let stationary: isize = offset;
+    let counter = timer + offset * 2;
+    let mut k = 0;
+
+    if timer < 0 {
+        timer = 5;
+    }
+
+    let mut rocket = String::new();
+
+    while k < counter {
+        rocket.clear();
+
+        print!("\x1b[2J");
+
+        newline_offset(&mut rocket, offset);
+
+        build_rocket_part(&mut rocket, "      /\\\n", offset);
+        build_rocket_part(&mut rocket, "     /  \\\n", offset+1);
+        build_rocket_part(&mut rocket, "    /    \\\n", offset+2);
+        build_rocket_part(&mut rocket, "    |  A |\n", offset+3);
+        build_rocket_part(&mut rocket, "    |  L |\n", offset+4);
+        build_rocket_part(&mut rocket, "   /|  T |\\\n", offset+5);
+        build_rocket_part(&mut rocket, "  / |  O | \\\n", offset+6);
+        build_rocket_part(&mut rocket, "  ^^|  S |^^\n", offset+7);
+        build_rocket_part(&mut rocket, "    |    |\n", offset+8);
+
+        if (offset % 2 == 0) && (offset < stationary) {
+            build_rocket_part(&mut rocket, "     vwwv", offset+9);
+        }
+
+        newline_offset(&mut rocket, stationary - offset);
+
+        print!("{}", rocket);
+
+        if timer < 0 {
+            if offset > -9 {
+                offset -= 1;
+            }
+            println!("\t\t/-------------\\     Blast off!");
+            delay_ms(100);
+        }
+        else {
+            println!("\t\t/-------------\\     Blast off in...{}", timer);
+            delay_ms(1000);
+        }
+
+        k += 1;
+        timer -= 1;
+    }
+}
+
+fn newline_offset(string: &mut String, offset: isize){
+    let mut i = 0;
+    if offset < 1 {
+        return;
+    }
+    while i < offset{
+        string.push_str("\n");
+        i += 1;
+    }
+}
+
+fn build_rocket_part(rocket: &mut String,part: &str, offset: isize) {
+    if offset < 0 {
+        return;
+    }
+    rocket.push_str("\t\t");
+    rocket.push_str(part);
+}
+*/
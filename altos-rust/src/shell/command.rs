@@ -0,0 +1,121 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The command registry. Builtin commands are always available, other modules can add their own
+// commands with `register` before the shell task starts.
+
+use kernel::sync::Mutex;
+use kernel::collections::Vec;
+use core::fmt::{self, Display};
+use super::Shell;
+use super::builtins::BUILTINS;
+#[cfg(target_arch = "arm")]
+use super::board::BOARD_BUILTINS;
+
+// There are no board commands when the shell is built for the host.
+#[cfg(not(target_arch = "arm"))]
+static BOARD_BUILTINS: [Command; 0] = [];
+
+/// The maximum number of commands that can be registered in addition to the builtins.
+pub const MAX_COMMANDS: usize = 16;
+
+static REGISTRY: Mutex<[Option<&'static Command>; MAX_COMMANDS]> = Mutex::new([None; MAX_COMMANDS]);
+
+/// The exit status of a command. `&&` runs the next command only if this is `SUCCESS`, `||` only
+/// if it isn't.
+pub type Status = u8;
+
+/// The command succeeded.
+pub const SUCCESS: Status = 0;
+/// The command failed, after printing why.
+pub const FAILURE: Status = 1;
+/// The command was given arguments it doesn't understand.
+pub const USAGE: Status = 2;
+/// There is no command with the name given.
+pub const NOT_FOUND: Status = 127;
+/// The command was stopped with Ctrl-C.
+pub const INTERRUPTED: Status = 130;
+
+/// A command that can be run from the shell.
+pub struct Command {
+    /// The name the command is invoked by.
+    pub name: &'static str,
+    /// A short synopsis of the command's arguments, listed by `help`.
+    pub usage: &'static str,
+    /// A description of the command, displayed by `help <cmd>`.
+    pub help: &'static str,
+    /// Called with the arguments following the command name, returns the command's exit status.
+    pub handler: fn(&mut Shell, &[&str]) -> Status,
+    /// Offers completions for the next argument, given the arguments typed so far.
+    pub complete: Option<fn(&Shell, &[&str]) -> Vec<&'static str>>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum RegisterError {
+    /// A command with the same name already exists.
+    Duplicate,
+    /// There is no more room in the registry.
+    Full,
+}
+
+impl Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            RegisterError::Duplicate => "a command with that name already exists",
+            RegisterError::Full => "too many commands registered",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Add a command to the shell.
+///
+/// Commands should be registered before the shell task is started. Builtin commands can not be
+/// replaced.
+pub fn register(command: &'static Command) -> Result<(), RegisterError> {
+    if find(command.name).is_some() {
+        return Err(RegisterError::Duplicate);
+    }
+    let mut registry = REGISTRY.lock();
+    for slot in registry.iter_mut() {
+        if slot.is_none() {
+            *slot = Some(command);
+            return Ok(());
+        }
+    }
+    Err(RegisterError::Full)
+}
+
+/// Look up a command by name.
+pub fn find(name: &str) -> Option<&'static Command> {
+    if let Some(command) = BUILTINS.iter().chain(BOARD_BUILTINS.iter()).find(|command| command.name == name) {
+        return Some(command);
+    }
+    let registry = REGISTRY.lock();
+    registry.iter()
+        .filter_map(|slot| *slot)
+        .find(|command| command.name == name)
+}
+
+/// All available commands, builtins first followed by registered commands in the order they
+/// were registered.
+pub fn commands() -> Vec<&'static Command> {
+    let mut commands: Vec<&'static Command> = BUILTINS.iter().chain(BOARD_BUILTINS.iter()).collect();
+    let registry = REGISTRY.lock();
+    commands.extend(registry.iter().filter_map(|slot| *slot));
+    commands
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Tab completion. The first word on a line completes to a command name, later words are completed
+// by the command itself if it provides a completion function.
+
+use kernel::collections::Vec;
+use super::Shell;
+use super::command;
+
+/// Find the completions for the last word in `line`.
+///
+/// Returns the partial word being completed and the candidates that start with it.
+pub fn complete<'a>(shell: &Shell, line: &'a str) -> (&'a str, Vec<&'static str>) {
+    let mut words: Vec<&str> = line.split(' ').collect();
+    let partial = words.pop().unwrap_or("");
+    words.retain(|word| !word.is_empty());
+
+    let candidates = match words.split_first() {
+        None => command::commands().iter().map(|command| command.name).collect(),
+        Some((name, args)) => match command::find(name).and_then(|command| command.complete) {
+            Some(complete) => complete(shell, args),
+            None => Vec::new(),
+        },
+    };
+    let candidates = candidates.into_iter()
+        .filter(|candidate| candidate.starts_with(partial))
+        .collect();
+    (partial, candidates)
+}
+
+/// The longest prefix shared by all of the candidates.
+pub fn common_prefix<'a>(candidates: &[&'a str]) -> &'a str {
+    let first = match candidates.first() {
+        Some(first) => *first,
+        None => return "",
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first.bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|&(a, b)| a == b)
+            .count();
+    }
+    &first[..len]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{complete, common_prefix};
+    use kernel::collections::{Vec, String};
+    use super::super::{Shell, Buffer};
+
+    // The partial word and the candidates for completing the line.
+    fn candidates(line: &str) -> (String, Vec<&'static str>) {
+        let mut console = Buffer::new("");
+        let shell = Shell::new(&mut console);
+        let (partial, candidates) = complete(&shell, line);
+        (String::from(partial), candidates)
+    }
+
+    // Type the keys at the prompt, returning what was written to the console.
+    fn type_line(keys: &str) -> String {
+        let mut console = Buffer::new(keys);
+        Shell::new(&mut console).read_and_execute();
+        String::from(console.output())
+    }
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(candidates("ec").0, "ec");
+        assert_eq!(candidates("ec").1, ["echo"]);
+        assert_eq!(candidates("un").1, ["unset", "unalias", "uname"]);
+        assert!(candidates("nope").1.is_empty());
+    }
+
+    #[test]
+    fn completes_arguments() {
+        assert_eq!(candidates("help  ec").0, "ec");
+        assert_eq!(candidates("help  ec").1, ["echo"]);
+        assert!(candidates("help echo e").1.is_empty());
+        assert!(candidates("echo ec").1.is_empty());
+    }
+
+    #[test]
+    fn finds_common_prefix() {
+        assert_eq!(common_prefix(&["unset", "unalias", "uname"]), "un");
+        assert_eq!(common_prefix(&["unalias", "uname"]), "una");
+        assert_eq!(common_prefix(&["echo"]), "echo");
+        assert_eq!(common_prefix(&[]), "");
+    }
+
+    #[test]
+    fn completes_on_tab() {
+        assert!(type_line("uns\tX\r").contains(" > unset X"));
+        assert!(type_line("help ec\t\r").contains(" > help echo \necho [string ...]"));
+        assert!(type_line("una\t\r").contains("\nunalias  uname  \n\r > una"));
+    }
+}
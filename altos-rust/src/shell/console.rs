@@ -0,0 +1,119 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The terminal the shell reads from and writes to. The shell only talks to a `Console`, so it can
+// be driven by something other than the UART, like a scripted buffer when running off target.
+
+#[cfg(target_arch = "arm")]
+use cortex_m0::io;
+use kernel::collections::{Vec, String};
+use core::fmt;
+
+/// A character device the shell can be attached to.
+pub trait Console: fmt::Write {
+    /// Get the next character typed, or `None` if nothing is waiting.
+    fn read_char(&mut self) -> Option<char>;
+
+    /// Returns true once there will never be any more input, so the shell should stop waiting for
+    /// it. A terminal is never closed.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// The serial console on the board.
+#[cfg(target_arch = "arm")]
+pub struct Uart;
+
+#[cfg(target_arch = "arm")]
+impl fmt::Write for Uart {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        print!("{}", string);
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl Console for Uart {
+    fn read_char(&mut self) -> Option<char> {
+        io::poll_char().map(|ch| ch as char)
+    }
+}
+
+/// The serial console without its input, for commands running in the background. Reading from it
+/// never returns anything, so those commands can't take the keys meant for the shell.
+#[cfg(target_arch = "arm")]
+pub struct UartOutput;
+
+#[cfg(target_arch = "arm")]
+impl fmt::Write for UartOutput {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        print!("{}", string);
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl Console for UartOutput {
+    fn read_char(&mut self) -> Option<char> {
+        None
+    }
+}
+
+/// A console that plays back a fixed sequence of keystrokes and records everything written to it.
+/// It is closed once all of the keystrokes have been read.
+pub struct Buffer {
+    input: Vec<char>,
+    position: usize,
+    output: String,
+}
+
+impl Buffer {
+    pub fn new(input: &str) -> Self {
+        Buffer {
+            input: input.chars().collect(),
+            position: 0,
+            output: String::new(),
+        }
+    }
+
+    /// Everything written to the console so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+}
+
+impl fmt::Write for Buffer {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.output.push_str(string);
+        Ok(())
+    }
+}
+
+impl Console for Buffer {
+    fn read_char(&mut self) -> Option<char> {
+        let ch = self.input.get(self.position).cloned();
+        if ch.is_some() {
+            self.position += 1;
+        }
+        ch
+    }
+
+    fn is_closed(&self) -> bool {
+        self.position == self.input.len()
+    }
+}
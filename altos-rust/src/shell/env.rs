@@ -0,0 +1,83 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Shell environment variables, set with `set` and expanded with `$NAME` or `${NAME}`.
+
+use kernel::collections::{Vec, String};
+use core::fmt::{self, Display};
+use core::slice;
+use super::eval::is_identifier;
+use super::table::Table;
+
+/// Variables provided by the shell itself, which can't be set or unset.
+pub const READ_ONLY: [&'static str; 2] = ["PROMPT", "UPTIME"];
+
+#[derive(Copy, Clone, Debug)]
+pub enum EnvError {
+    InvalidName,
+    ReadOnly,
+}
+
+impl Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            EnvError::InvalidName => "names must start with a letter or underscore and contain only letters, digits and underscores",
+            EnvError::ReadOnly => "variable is read-only",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+pub struct Environment {
+    vars: Table<String>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            vars: Table::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|value| value.as_ref())
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), EnvError> {
+        if READ_ONLY.contains(&name) {
+            return Err(EnvError::ReadOnly);
+        }
+        if !is_identifier(name) {
+            return Err(EnvError::InvalidName);
+        }
+        self.vars.set(name, String::from(value));
+        Ok(())
+    }
+
+    /// Remove a variable, returning false if it wasn't set.
+    pub fn unset(&mut self, name: &str) -> Result<bool, EnvError> {
+        if READ_ONLY.contains(&name) {
+            return Err(EnvError::ReadOnly);
+        }
+        Ok(self.vars.remove(name))
+    }
+
+    /// The variables in the order they were first set.
+    pub fn iter(&self) -> slice::Iter<(String, String)> {
+        self.vars.iter()
+    }
+}
@@ -0,0 +1,562 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The expression calculator used by the `eval` command.
+
+use kernel::collections::{Vec, String};
+use kernel::alloc::Box;
+use core::fmt::{self, Display};
+use core::iter::Peekable;
+use core::str::CharIndices;
+use super::table::Table;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Token<'a> {
+    Number(isize),
+    Ident(&'a str),
+    Op(Operator),
+    Equals,
+    Tilde,
+    LeftParen,
+    RightParen,
+    EOF,
+}
+
+// Errors carry the column (starting at 0) of the character or token that caused them.
+#[derive(Copy, Clone, Debug)]
+pub enum LexError {
+    InvalidToken(usize),
+    InvalidNumber(usize),
+}
+
+impl LexError {
+    pub fn column(&self) -> usize {
+        match *self {
+            LexError::InvalidToken(column) | LexError::InvalidNumber(column) => column,
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            LexError::InvalidToken(_) => "invalid character",
+            LexError::InvalidNumber(_) => "invalid number",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Parse a number literal, either decimal or hexadecimal/binary with a `0x`/`0b` prefix.
+///
+/// Hexadecimal and binary literals are taken as the bit pattern of the number, so `0xFFFFFFFF`
+/// is accepted even though it is larger than `isize::MAX` on a 32 bit machine.
+pub fn parse_number(literal: &str) -> Option<isize> {
+    if literal.starts_with("0x") || literal.starts_with("0X") {
+        usize::from_str_radix(&literal[2..], 16).ok().map(|num| num as isize)
+    }
+    else if literal.starts_with("0b") || literal.starts_with("0B") {
+        usize::from_str_radix(&literal[2..], 2).ok().map(|num| num as isize)
+    }
+    else {
+        literal.parse::<isize>().ok()
+    }
+}
+
+/// Returns true if the string can be used as a variable name.
+///
+/// Names start with a letter or underscore, followed by letters, digits or underscores.
+pub fn is_identifier(string: &str) -> bool {
+    let mut chars = string.chars();
+    match chars.next() {
+        Some(ch) if is_identifier_start(ch) => chars.all(is_identifier_char),
+        _ => false,
+    }
+}
+
+pub fn is_identifier_start(ch: char) -> bool {
+    (ch >= 'a' && ch <= 'z') || (ch >= 'A' && ch <= 'Z') || ch == '_'
+}
+
+pub fn is_identifier_char(ch: char) -> bool {
+    is_identifier_start(ch) || (ch >= '0' && ch <= '9')
+}
+
+/// Variables assigned with `eval`, kept for the rest of the shell session.
+pub type Variables = Table<isize>;
+
+pub struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Split an expression into tokens, each paired with the column it starts at.
+    ///
+    /// Whitespace between tokens is optional.
+    pub fn lex(source: &'a str) -> Result<Vec<(Token<'a>, usize)>, LexError> {
+        let mut lexer = Lexer {
+            source: source,
+            chars: source.char_indices().peekable(),
+        };
+        let mut tokens = Vec::new();
+        while let Some((start, ch)) = lexer.chars.next() {
+            let token = match ch {
+                ' ' | '\t' => continue,
+                '+' => Token::Op(Operator::Add),
+                '-' => Token::Op(Operator::Sub),
+                '*' => Token::Op(Operator::Mul),
+                '/' => Token::Op(Operator::Div),
+                '%' => Token::Op(Operator::Rem),
+                '&' => Token::Op(Operator::And),
+                '|' => Token::Op(Operator::Or),
+                '^' => Token::Op(Operator::Xor),
+                '<' if lexer.next_is('<') => Token::Op(Operator::Shl),
+                '>' if lexer.next_is('>') => Token::Op(Operator::Shr),
+                '~' => Token::Tilde,
+                '=' => Token::Equals,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '0'...'9' => {
+                    // Take letters too so hex digits and the 0x/0b prefixes are part of the literal.
+                    let literal = lexer.take_while(start, is_identifier_char);
+                    match parse_number(literal) {
+                        Some(num) => Token::Number(num),
+                        None => return Err(LexError::InvalidNumber(start)),
+                    }
+                },
+                ch if is_identifier_start(ch) => Token::Ident(lexer.take_while(start, is_identifier_char)),
+                _ => return Err(LexError::InvalidToken(start)),
+            };
+            tokens.push((token, start));
+        }
+        tokens.push((Token::EOF, source.len()));
+        Ok(tokens)
+    }
+
+    // Consume the next character if it is `expected`.
+    fn next_is(&mut self, expected: char) -> bool {
+        match self.chars.peek() {
+            Some(&(_, ch)) if ch == expected => {
+                self.chars.next();
+                true
+            },
+            _ => false,
+        }
+    }
+
+    // Consume characters while they match the predicate, returning the source from `start` on.
+    fn take_while(&mut self, start: usize, predicate: fn(char) -> bool) -> &'a str {
+        let mut end = self.source.len();
+        while let Some(&(index, ch)) = self.chars.peek() {
+            if !predicate(ch) {
+                end = index;
+                break;
+            }
+            self.chars.next();
+        }
+        &self.source[start..end]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum ParseError {
+    UnexpectedToken(usize),
+    UnmatchedParens(usize),
+    InvalidOperator(usize),
+}
+
+impl ParseError {
+    pub fn column(&self) -> usize {
+        match *self {
+            ParseError::UnexpectedToken(column) |
+            ParseError::UnmatchedParens(column) |
+            ParseError::InvalidOperator(column) => column,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            ParseError::UnexpectedToken(_) => "unexpected token",
+            ParseError::UnmatchedParens(_) => "unmatched parenthesis",
+            ParseError::InvalidOperator(_) => "invalid operator",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum EvalError<'a> {
+    DivideByZero,
+    Overflow,
+    UndefinedVariable(&'a str),
+}
+
+impl<'a> Display for EvalError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EvalError::DivideByZero => write!(f, "division by zero"),
+            EvalError::Overflow => write!(f, "overflow"),
+            EvalError::UndefinedVariable(name) => write!(f, "undefined variable '{}'", name),
+        }
+    }
+}
+
+// Grammar, from lowest to highest precedence:
+//
+// statement := IDENTIFIER "=" expression | expression
+// expression := bit_or
+// bit_or := bit_xor ( "|" bit_xor )*
+// bit_xor := bit_and ( "^" bit_and )*
+// bit_and := shift ( "&" shift )*
+// shift := term ( ( "<<" | ">>" ) term )*
+// term := factor ( ( "-" | "+" ) factor )*
+// factor := unary ( ( "*" | "/" | "%" ) unary )*
+// unary := ( "-" | "~" ) unary | primary
+// primary := NUMBER | IDENTIFIER | "(" expression ")"
+pub struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    current: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<(Token<'a>, usize)>) -> Self {
+        Parser {
+            tokens: tokens,
+            current: 0,
+        }
+    }
+
+    fn matches(&mut self, op: Operator) -> bool {
+        if self.is_at_end() {
+            false
+        }
+        else {
+            match self.peek() {
+                Token::Op(operator) if op == operator => {
+                    self.advance();
+                    true
+                },
+                _ => false
+            }
+        }
+    }
+
+    fn advance(&mut self) -> Token<'a> {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek() == Token::EOF
+    }
+
+    fn peek(&self) -> Token<'a> {
+        self.tokens[self.current].0
+    }
+
+    fn peek_next(&self) -> Token<'a> {
+        if self.is_at_end() {
+            Token::EOF
+        }
+        else {
+            self.tokens[self.current + 1].0
+        }
+    }
+
+    fn previous(&self) -> Token<'a> {
+        self.tokens[self.current - 1].0
+    }
+
+    // The column of the next token.
+    fn column(&self) -> usize {
+        self.tokens[self.current].1
+    }
+
+    fn previous_operator(&self) -> Result<Operator, ParseError> {
+        if let Token::Op(op) = self.previous() {
+            Ok(op)
+        }
+        else {
+            Err(ParseError::InvalidOperator(self.tokens[self.current - 1].1))
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Statement<'a>, ParseError> {
+        let statement = self.statement()?;
+        if self.is_at_end() {
+            Ok(statement)
+        }
+        else {
+            Err(ParseError::UnexpectedToken(self.column()))
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement<'a>, ParseError> {
+        match (self.peek(), self.peek_next()) {
+            (Token::Ident(name), Token::Equals) => {
+                self.advance();
+                self.advance();
+                let expr = self.expression()?;
+                Ok(Statement::Assign(name, expr))
+            },
+            _ => Ok(Statement::Expr(self.expression()?)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr<'a>, ParseError> {
+        self.bit_or()
+    }
+
+    fn bit_or(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.matches(Operator::Or) {
+            let right = self.bit_xor()?;
+            expr = Expr::Op(Box::new(expr), Operator::Or, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.bit_and()?;
+
+        while self.matches(Operator::Xor) {
+            let right = self.bit_and()?;
+            expr = Expr::Op(Box::new(expr), Operator::Xor, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.shift()?;
+
+        while self.matches(Operator::And) {
+            let right = self.shift()?;
+            expr = Expr::Op(Box::new(expr), Operator::And, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.term()?;
+
+        while self.matches(Operator::Shl) || self.matches(Operator::Shr) {
+            let operator = self.previous_operator()?;
+            let right = self.term()?;
+            expr = Expr::Op(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.matches(Operator::Add) || self.matches(Operator::Sub) {
+            let operator = self.previous_operator()?;
+            let right = self.factor()?;
+            expr = Expr::Op(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr<'a>, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.matches(Operator::Mul) || self.matches(Operator::Div) || self.matches(Operator::Rem) {
+            let operator = self.previous_operator()?;
+            let right = self.unary()?;
+            expr = Expr::Op(Box::new(expr), operator, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr<'a>, ParseError> {
+        if self.matches(Operator::Sub) {
+            let expr = self.unary()?;
+            Ok(Expr::Neg(Box::new(expr)))
+        }
+        else if self.peek() == Token::Tilde {
+            self.advance();
+            let expr = self.unary()?;
+            Ok(Expr::Not(Box::new(expr)))
+        }
+        else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr<'a>, ParseError> {
+        let column = self.column();
+        match self.advance() {
+            Token::Number(num) => Ok(Expr::Val(num)),
+            Token::Ident(name) => Ok(Expr::Var(name)),
+            Token::LeftParen => {
+                let expr = self.expression()?;
+                if let Token::RightParen = self.advance() {
+                    Ok(expr)
+                }
+                else {
+                    Err(ParseError::UnmatchedParens(column))
+                }
+            },
+            _ => Err(ParseError::UnexpectedToken(column)),
+        }
+    }
+}
+
+pub enum Statement<'a> {
+    Assign(&'a str, Expr<'a>),
+    Expr(Expr<'a>),
+}
+
+pub enum Expr<'a> {
+    Op(Box<Expr<'a>>, Operator, Box<Expr<'a>>),
+    Neg(Box<Expr<'a>>),
+    Not(Box<Expr<'a>>),
+    Var(&'a str),
+    Val(isize),
+}
+
+impl<'a> Expr<'a> {
+    pub fn eval(&self, vars: &Variables) -> Result<isize, EvalError<'a>> {
+        match *self {
+            Expr::Op(ref lhs, ref op, ref rhs) => op.apply(lhs.eval(vars)?, rhs.eval(vars)?),
+            Expr::Neg(ref expr) => expr.eval(vars)?.checked_neg().ok_or(EvalError::Overflow),
+            Expr::Not(ref expr) => Ok(!expr.eval(vars)?),
+            Expr::Var(name) => vars.get(name).cloned().ok_or(EvalError::UndefinedVariable(name)),
+            Expr::Val(x) => Ok(x),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+impl Operator {
+    fn apply<'a>(&self, lhs: isize, rhs: isize) -> Result<isize, EvalError<'a>> {
+        let result = match *self {
+            Operator::Add => lhs.checked_add(rhs),
+            Operator::Sub => lhs.checked_sub(rhs),
+            Operator::Mul => lhs.checked_mul(rhs),
+            Operator::Div | Operator::Rem if rhs == 0 => return Err(EvalError::DivideByZero),
+            Operator::Div => lhs.checked_div(rhs),
+            Operator::Rem => lhs.checked_rem(rhs),
+            Operator::And => Some(lhs & rhs),
+            Operator::Or => Some(lhs | rhs),
+            Operator::Xor => Some(lhs ^ rhs),
+            // Negative shift amounts wrap around to very large ones, which are rejected too.
+            Operator::Shl => lhs.checked_shl(rhs as u32),
+            Operator::Shr => lhs.checked_shr(rhs as u32),
+        };
+        result.ok_or(EvalError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, Parser, Statement, Variables, parse_number};
+    use kernel::collections::String;
+    use std::string::ToString;
+
+    // Run a statement the way `eval` does, returning the result or the error message along with the
+    // column it points at for lexing and parsing errors.
+    fn eval(vars: &mut Variables, source: &str) -> Result<isize, (String, Option<usize>)> {
+        let tokens = Lexer::lex(source).map_err(|err| (err.to_string(), Some(err.column())))?;
+        let statement = Parser::new(tokens).parse().map_err(|err| (err.to_string(), Some(err.column())))?;
+        match statement {
+            Statement::Assign(name, expr) => {
+                let value = expr.eval(vars).map_err(|err| (err.to_string(), None))?;
+                vars.set(name, value);
+                Ok(value)
+            },
+            Statement::Expr(expr) => expr.eval(vars).map_err(|err| (err.to_string(), None)),
+        }
+    }
+
+    fn value(source: &str) -> isize {
+        eval(&mut Variables::new(), source).unwrap()
+    }
+
+    fn error(source: &str) -> (String, Option<usize>) {
+        eval(&mut Variables::new(), source).unwrap_err()
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("0x1F"), Some(31));
+        assert_eq!(parse_number("0B101"), Some(5));
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("12a"), None);
+    }
+
+    #[test]
+    fn follows_precedence() {
+        assert_eq!(value("1 + 2 * 3"), 7);
+        assert_eq!(value("(1 + 2) * 3"), 9);
+        assert_eq!(value("10 - 4 - 3"), 3);
+        assert_eq!(value("1 << 2 + 1"), 8);
+        assert_eq!(value("0xF0 | 0b1010 & ~2"), 248);
+        assert_eq!(value("6 ^ 3 & 1"), 7);
+        assert_eq!(value("-3 * 2 + 7 % 4"), -3);
+        assert_eq!(value("--1"), 1);
+    }
+
+    #[test]
+    fn checks_arithmetic() {
+        assert_eq!(error("1 / 0").0, "division by zero");
+        assert_eq!(error("5 % 0").0, "division by zero");
+        assert_eq!(error("0x7FFFFFFFFFFFFFFF * 2").0, "overflow");
+        assert_eq!(error("1 << 64").0, "overflow");
+        assert_eq!(error("1 >> -1").0, "overflow");
+    }
+
+    #[test]
+    fn assigns_variables() {
+        let mut vars = Variables::new();
+        assert_eq!(eval(&mut vars, "x = 4 * 1024"), Ok(4096));
+        assert_eq!(eval(&mut vars, "x / 3"), Ok(1365));
+        assert_eq!(eval(&mut vars, "x = x + 1"), Ok(4097));
+        assert_eq!(vars.get("x"), Some(&4097));
+        assert_eq!(eval(&mut vars, "y + 1").unwrap_err().0, "undefined variable 'y'");
+    }
+
+    #[test]
+    fn points_at_errors() {
+        assert_eq!(error("1 + $"), (String::from("invalid character"), Some(4)));
+        assert_eq!(error("3x"), (String::from("invalid number"), Some(0)));
+        assert_eq!(error("(1 + 2"), (String::from("unmatched parenthesis"), Some(0)));
+        assert_eq!(error("1 2"), (String::from("unexpected token"), Some(2)));
+        assert_eq!(error("1 +"), (String::from("unexpected token"), Some(3)));
+    }
+}
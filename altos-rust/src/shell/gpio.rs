@@ -0,0 +1,338 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The `gpio` command, for reading and driving pins by name, and the pins claimed by tasks. Tasks
+// that drive a pin should claim it with `claim_pin` so `gpio` won't change it from under them.
+
+use cortex_m0::peripheral::gpio::{Port, Group, Mode, Pull};
+use kernel::sync::Mutex;
+use kernel::collections::Vec;
+use core::fmt::{self, Display};
+use core::ptr;
+use tasks;
+use super::{Shell, RegisterError};
+use super::command::{Status, SUCCESS, FAILURE, USAGE};
+
+/// The maximum number of pins that can be claimed in addition to the builtin claims.
+pub const MAX_CLAIMS: usize = 8;
+
+/// The user LED on the board.
+pub const LED: Pin = Pin { group: Group::B, number: 3 };
+
+pub const GPIO_USAGE: &'static str = "gpio <pin> read | gpio <pin> out high|low | gpio <pin> mode <mode> [pull] | gpio list";
+
+static CLAIMS: Mutex<[Option<Claim>; MAX_CLAIMS]> = Mutex::new([None; MAX_CLAIMS]);
+
+static BUILTIN_CLAIMS: [Claim; 1] = [
+    Claim { pin: LED, task: "blink" },
+];
+
+const GROUPS: [Group; 4] = [Group::A, Group::B, Group::C, Group::F];
+
+// RCC's AHB peripheral clock enable register, which has a bit for each GPIO group.
+const RCC_AHBENR: usize = 0x4002_1014;
+
+// The names of every pin, for completion.
+const PIN_NAMES: [&'static str; 64] = [
+    "PA0", "PA1", "PA2", "PA3", "PA4", "PA5", "PA6", "PA7",
+    "PA8", "PA9", "PA10", "PA11", "PA12", "PA13", "PA14", "PA15",
+    "PB0", "PB1", "PB2", "PB3", "PB4", "PB5", "PB6", "PB7",
+    "PB8", "PB9", "PB10", "PB11", "PB12", "PB13", "PB14", "PB15",
+    "PC0", "PC1", "PC2", "PC3", "PC4", "PC5", "PC6", "PC7",
+    "PC8", "PC9", "PC10", "PC11", "PC12", "PC13", "PC14", "PC15",
+    "PF0", "PF1", "PF2", "PF3", "PF4", "PF5", "PF6", "PF7",
+    "PF8", "PF9", "PF10", "PF11", "PF12", "PF13", "PF14", "PF15",
+];
+
+/// A single GPIO pin, named like `PB3`.
+#[derive(Copy, Clone)]
+pub struct Pin {
+    pub group: Group,
+    pub number: u8,
+}
+
+impl Pin {
+    /// Parse a pin name like `PA5` or `pc13`.
+    pub fn parse(name: &str) -> Option<Pin> {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some('P') | Some('p') => {},
+            _ => return None,
+        }
+        let group = match chars.next() {
+            Some('A') | Some('a') => Group::A,
+            Some('B') | Some('b') => Group::B,
+            Some('C') | Some('c') => Group::C,
+            Some('F') | Some('f') => Group::F,
+            _ => return None,
+        };
+        match chars.as_str().parse::<u8>() {
+            Ok(number) if number < 16 => Some(Pin { group: group, number: number }),
+            _ => None,
+        }
+    }
+
+    /// The port for this pin. Its registers read as zero unless the group's clock is enabled.
+    pub fn port(&self) -> Port {
+        Port::new(self.number, self.group)
+    }
+
+    /// The port for this pin, enabling its group's clock first so it can be driven.
+    pub fn enabled_port(&self) -> Port {
+        Port::enable(self.group);
+        self.port()
+    }
+
+    /// Whether the clock for the pin's group is enabled.
+    pub fn is_enabled(&self) -> bool {
+        let bit = match self.group {
+            Group::A => 17,
+            Group::B => 18,
+            Group::C => 19,
+            Group::F => 22,
+        };
+        let ahbenr = unsafe { ptr::read_volatile(RCC_AHBENR as *const u32) };
+        ahbenr & (1 << bit) != 0
+    }
+
+    /// The letter of the pin's group.
+    pub fn letter(&self) -> char {
+        match self.group {
+            Group::A => 'A',
+            Group::B => 'B',
+            Group::C => 'C',
+            Group::F => 'F',
+        }
+    }
+}
+
+impl PartialEq for Pin {
+    fn eq(&self, other: &Pin) -> bool {
+        self.letter() == other.letter() && self.number == other.number
+    }
+}
+
+impl Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "P{}{}", self.letter(), self.number)
+    }
+}
+
+/// A pin used by the task with the given name. The claim only counts while a task with that name
+/// is running.
+#[derive(Copy, Clone)]
+pub struct Claim {
+    pub pin: Pin,
+    pub task: &'static str,
+}
+
+/// Mark a pin as being used by the tasks named `task`.
+///
+/// Pins should be claimed before the shell task is started.
+pub fn claim_pin(pin: Pin, task: &'static str) -> Result<(), RegisterError> {
+    let mut claims = CLAIMS.lock();
+    if claims.iter().filter_map(|slot| *slot).chain(BUILTIN_CLAIMS.iter().cloned())
+        .any(|claim| claim.pin == pin && claim.task == task) {
+        return Err(RegisterError::Duplicate);
+    }
+    for slot in claims.iter_mut() {
+        if slot.is_none() {
+            *slot = Some(Claim { pin: pin, task: task });
+            return Ok(());
+        }
+    }
+    Err(RegisterError::Full)
+}
+
+/// The name of the running task using a pin, if there is one.
+pub fn claimed_by(pin: Pin) -> Option<&'static str> {
+    let running = tasks::tasks();
+    let claims = CLAIMS.lock();
+    let mut all = BUILTIN_CLAIMS.iter().cloned().chain(claims.iter().filter_map(|slot| *slot));
+    all.find(|claim| claim.pin == pin && running.iter().any(|task| task.name == claim.task))
+        .map(|claim| claim.task)
+}
+
+pub fn gpio(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() == 1 && args[0] == "list" {
+        return list(shell);
+    }
+    let pin = match args.first().and_then(|name| Pin::parse(name)) {
+        Some(pin) => pin,
+        None => {
+            cprintln!(shell.console, "Usage: {}", GPIO_USAGE);
+            return USAGE;
+        },
+    };
+
+    match (args.get(1).cloned(), args.len()) {
+        (Some("read"), 2) => {
+            // Reading a pin shouldn't change anything, so a group that is off is left off.
+            if pin.is_enabled() {
+                let port = pin.port();
+                cprint!(shell.console, "{} {} {}", pin, mode_name(port.get_mode()), level_name(port.read()));
+            }
+            else {
+                cprint!(shell.console, "{} off, GPIO{} is not enabled", pin, pin.letter());
+            }
+            if let Some(task) = claimed_by(pin) {
+                cprint!(shell.console, " (used by {})", task);
+            }
+            cprintln!(shell.console);
+            SUCCESS
+        },
+        (Some("out"), 3) => {
+            let high = match args[2] {
+                "high" | "1" => true,
+                "low" | "0" => false,
+                _ => {
+                    cprintln!(shell.console, "Usage: {}", GPIO_USAGE);
+                    return USAGE;
+                },
+            };
+            if !check_unclaimed(shell, pin) {
+                return FAILURE;
+            }
+            let mut port = pin.enabled_port();
+            port.set_mode(Mode::Output);
+            if high {
+                port.set();
+            }
+            else {
+                port.reset();
+            }
+            SUCCESS
+        },
+        (Some("mode"), 3) | (Some("mode"), 4) => {
+            let mode = parse_mode(args[2]);
+            let pull = match args.get(3) {
+                Some(pull) => parse_pull(pull),
+                None => Some(Pull::Neither),
+            };
+            let (mode, pull) = match (mode, pull) {
+                (Some(mode), Some(pull)) => (mode, pull),
+                _ => {
+                    cprintln!(shell.console, "Modes are input, output, alternate or analog, pulls are pullup, pulldown or nopull");
+                    return USAGE;
+                },
+            };
+            if !check_unclaimed(shell, pin) {
+                return FAILURE;
+            }
+            let mut port = pin.enabled_port();
+            port.set_mode(mode);
+            port.set_pull(pull);
+            SUCCESS
+        },
+        _ => {
+            cprintln!(shell.console, "Usage: {}", GPIO_USAGE);
+            USAGE
+        },
+    }
+}
+
+// List every pin that isn't a plain input, which all pins are after reset except the debug pins,
+// or is claimed by a running task. Groups whose clock is off are left off, and only their claimed
+// pins are shown.
+fn list(shell: &mut Shell) -> Status {
+    cprintln!(shell.console, "{:<6}{:<11}{:<6}{:<10}{}", "PIN", "MODE", "LEVEL", "PULL", "USED BY");
+    for &group in &GROUPS {
+        for number in 0..16 {
+            let pin = Pin { group: group, number: number };
+            let task = claimed_by(pin);
+            if !pin.is_enabled() {
+                if let Some(task) = task {
+                    cprintln!(shell.console, "P{}{:<4}{:<11}{:<6}{:<10}{}", pin.letter(), pin.number, "off", "", "", task);
+                }
+                continue;
+            }
+            let port = pin.port();
+            let mode = port.get_mode();
+            if let (Mode::Input, None) = (mode, task) {
+                continue;
+            }
+            cprintln!(shell.console, "P{}{:<4}{:<11}{:<6}{:<10}{}",
+                pin.letter(), pin.number, mode_name(mode), level_name(port.read()), pull_name(port.get_pull()), task.unwrap_or(""));
+        }
+    }
+    SUCCESS
+}
+
+pub fn complete_gpio(_shell: &Shell, args: &[&str]) -> Vec<&'static str> {
+    if args.len() == 0 {
+        return Some("list").into_iter().chain(PIN_NAMES.iter().cloned()).collect();
+    }
+    let words: &[&'static str] = match (args.len(), args.get(1).cloned()) {
+        (1, _) if Pin::parse(args[0]).is_some() => &["read", "out", "mode"],
+        (2, Some("out")) => &["high", "low"],
+        (2, Some("mode")) => &["input", "output", "alternate", "analog"],
+        (3, Some("mode")) => &["pullup", "pulldown", "nopull"],
+        _ => &[],
+    };
+    words.iter().cloned().collect()
+}
+
+// Print a message and return false if the pin is in use by a running task.
+fn check_unclaimed(shell: &mut Shell, pin: Pin) -> bool {
+    match claimed_by(pin) {
+        Some(task) => {
+            cprintln!(shell.console, "{} is in use by task '{}'", pin, task);
+            false
+        },
+        None => true,
+    }
+}
+
+fn parse_mode(string: &str) -> Option<Mode> {
+    match string {
+        "input" | "in" => Some(Mode::Input),
+        "output" | "out" => Some(Mode::Output),
+        "alternate" => Some(Mode::Alternate),
+        "analog" => Some(Mode::Analog),
+        _ => None,
+    }
+}
+
+fn parse_pull(string: &str) -> Option<Pull> {
+    match string {
+        "pullup" => Some(Pull::Up),
+        "pulldown" => Some(Pull::Down),
+        "nopull" => Some(Pull::Neither),
+        _ => None,
+    }
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Input => "input",
+        Mode::Output => "output",
+        Mode::Alternate => "alternate",
+        Mode::Analog => "analog",
+    }
+}
+
+fn pull_name(pull: Pull) -> &'static str {
+    match pull {
+        Pull::Up => "pullup",
+        Pull::Down => "pulldown",
+        Pull::Neither => "nopull",
+    }
+}
+
+fn level_name(high: bool) -> &'static str {
+    if high { "high" } else { "low" }
+}
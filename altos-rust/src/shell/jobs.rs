@@ -0,0 +1,200 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Commands started in the background with a trailing `&`. Each job runs in its own task with a
+// shell of its own, which writes to the UART but never reads from it so it can't take the keys
+// typed at the prompt. The job's shell starts out empty, so commands that need the variables,
+// scripts or jobs of the shell at the prompt, or that take over the console, can't be jobs.
+//
+// Jobs can be destroyed at any point, and anything a destroyed task had allocated is never freed.
+// So the job's words belong to the `Job` rather than its task, and the task runs the command
+// without allocating anything itself. Heap used by the command while it runs is still lost if the
+// job is killed.
+
+use kernel::task::{TaskHandle, Priority};
+use kernel::task::args::{Args, ArgsBuilder};
+use kernel::collections::{Vec, String};
+use kernel::alloc::Box;
+use core::fmt::{self, Display};
+use core::slice;
+use tasks;
+use super::{Shell, UartOutput};
+
+/// The most jobs that can be running at once. Each one needs a stack from the small heap.
+pub const MAX_JOBS: usize = 2;
+
+/// The most words a job's command can have, including the command name.
+pub const MAX_JOB_WORDS: usize = 8;
+
+/// The stack size of a job's task.
+pub const JOB_STACK_SIZE: usize = 768;
+
+// Commands that only work in the foreground. Most use the shell's variables, scripts, aliases or
+// jobs, which a job's shell doesn't have. `run` and `watch` also run other commands, which
+// allocates in the job's task, and `watch` clears the screen.
+const FOREGROUND_ONLY: [&'static str; 14] = [
+    "eval", "vars", "set", "unset", "env", "script", "run", "alias", "unalias", "watch", "status",
+    "jobs", "fg", "exit",
+];
+
+#[derive(Copy, Clone, Debug)]
+pub enum JobError {
+    /// `MAX_JOBS` jobs are already running.
+    Full,
+    /// The command has more than `MAX_JOB_WORDS` words.
+    TooManyWords,
+    /// The command needs the shell at the prompt, so it can't run in the background.
+    ForegroundOnly(&'static str),
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            JobError::Full => "too many jobs running",
+            JobError::TooManyWords => "too many arguments for a job",
+            JobError::ForegroundOnly(name) => return write!(f, "{} only works in the foreground", name),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+pub struct Job {
+    /// The number used to refer to the job with `fg` and `kill %N`.
+    pub id: usize,
+    /// The name of the command being run, also used as the task's name.
+    pub name: &'static str,
+    pub handle: TaskHandle,
+    /// The words of the command joined back into a line.
+    pub command: String,
+    // The words the job's task runs. The task only borrows them, so they must not be dropped
+    // before the task has finished or been destroyed.
+    words: Box<Vec<String>>,
+}
+
+impl Job {
+    /// Returns true if the job's task has not exited or been destroyed.
+    pub fn is_alive(&self) -> bool {
+        self.handle.tid().is_ok()
+    }
+}
+
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Jobs {
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Start running the command `name` with its arguments in a new task, returning the job number.
+    pub fn start(&mut self, name: &'static str, words: Vec<String>) -> Result<usize, JobError> {
+        if FOREGROUND_ONLY.contains(&name) {
+            return Err(JobError::ForegroundOnly(name));
+        }
+        if self.jobs.iter().filter(|job| job.is_alive()).count() >= MAX_JOBS {
+            return Err(JobError::Full);
+        }
+        if words.len() > MAX_JOB_WORDS {
+            return Err(JobError::TooManyWords);
+        }
+        let mut command = String::new();
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                command.push(' ');
+            }
+            command.push_str(word);
+        }
+
+        // Task arguments can only be numbers, so hand the words over as a pointer.
+        let words = Box::new(words);
+        let mut args = ArgsBuilder::with_capacity(1);
+        args.add_num(&*words as *const Vec<String> as usize);
+        // Jobs run at a low priority like `blink`, so they don't hold up the shell.
+        let handle = tasks::new_task(job_task, args.finalize(), JOB_STACK_SIZE, Priority::Low, name);
+
+        let id = (1..).find(|id| !self.jobs.iter().any(|job| job.id == *id)).unwrap();
+        self.jobs.push(Job {
+            id: id,
+            name: name,
+            handle: handle,
+            command: command,
+            words: words,
+        });
+        Ok(id)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// The job started most recently.
+    pub fn last(&self) -> Option<&Job> {
+        self.jobs.last()
+    }
+
+    /// Stop keeping track of a job. Its task must have finished or been destroyed first.
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        match self.jobs.iter().position(|job| job.id == id) {
+            Some(index) => Some(self.jobs.remove(index)),
+            None => None,
+        }
+    }
+
+    /// Forget the jobs that have finished.
+    pub fn remove_finished(&mut self) {
+        self.jobs.retain(|job| job.is_alive());
+    }
+
+    /// The jobs in the order they were started.
+    pub fn iter(&self) -> slice::Iter<Job> {
+        self.jobs.iter()
+    }
+}
+
+// A job's words are freed along with it, so its task can't be left running. The board's shell
+// never returns, so this only happens to the shell of a job that started jobs of its own.
+impl Drop for Jobs {
+    fn drop(&mut self) {
+        for job in self.jobs.iter_mut() {
+            if job.is_alive() {
+                job.handle.destroy();
+            }
+        }
+    }
+}
+
+/// Parse a job number given as `%N` or `N`.
+pub fn parse_id(string: &str) -> Option<usize> {
+    let number = if string.starts_with('%') { &string[1..] } else { string };
+    number.parse::<usize>().ok()
+}
+
+fn job_task(args: &mut Args) {
+    let words = unsafe { &*(args.pop_num() as *const Vec<String>) };
+    // Aliases were expanded before the job started, so run the command directly. The arguments go
+    // in an array on the stack rather than a `Vec`, which would be lost if the job is killed.
+    let mut argv = [""; MAX_JOB_WORDS];
+    for (arg, word) in argv.iter_mut().zip(words.iter()) {
+        *arg = word;
+    }
+    let mut console = UartOutput;
+    let mut shell = Shell::new(&mut console);
+    shell.execute(argv[0], &argv[1..words.len()]);
+}
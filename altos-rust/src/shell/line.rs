@@ -0,0 +1,623 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Line editing for the shell. Understands the ANSI escape sequences sent by the arrow, Home, End
+// and Delete keys and the usual control keys, keeps a history of previously entered lines that can
+// be searched with Ctrl-R and completes words on Tab.
+
+use time::delay_ms;
+use kernel::collections::{Vec, String};
+use super::{Shell, Console};
+use super::complete;
+
+/// The number of lines kept in the history.
+pub const HISTORY_SIZE: usize = 16;
+
+const ESC: char = '\x1b';
+const TAB: char = '\t';
+// 8 is \b, but Rust doesn't recognize \b?
+const BACKSPACE: char = '\x08';
+const DEL: char = '\x7f';
+pub const CTRL_C: char = '\x03';
+const CTRL_D: char = '\x04';
+const CTRL_L: char = '\x0c';
+const CTRL_U: char = '\x15';
+const CTRL_W: char = '\x17';
+const CTRL_R: char = '\x12';
+
+// How long to wait after an ESC for the rest of an escape sequence before taking it as the Esc key
+// on its own.
+const ESC_TIMEOUT_MS: usize = 20;
+
+/// Why a line wasn't read.
+pub enum Interrupt {
+    /// Ctrl-C was pressed, throwing away the line.
+    Cancel,
+    /// Ctrl-D was pressed on an empty line, or the console was closed.
+    EndOfInput,
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Cancel,
+    EndOfInput,
+    ClearScreen,
+    KillLine,
+    DeleteWord,
+    Search,
+    Escape,
+    Unknown,
+    // The console was closed.
+    Closed,
+}
+
+/// A bounded list of previously entered lines.
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add a line to the history, dropping the oldest line if the history is full.
+    ///
+    /// Blank lines and repeats of the most recent line are not recorded.
+    pub fn push(&mut self, line: &str) {
+        if line.trim().is_empty() || self.get(0) == Some(line) {
+            return;
+        }
+        if self.entries.len() == HISTORY_SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push(String::from(line));
+    }
+
+    /// Get a line from the history, where 0 is the most recent line.
+    pub fn get(&self, back: usize) -> Option<&str> {
+        if back < self.entries.len() {
+            Some(&self.entries[self.entries.len() - back - 1])
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// The line only ever holds printable ASCII, which is all `Key::Char` accepts, so the cursor and
+// lengths can count bytes.
+struct LineEditor<'a, 'b: 'a> {
+    line: String,
+    cursor: usize,
+    prompt: &'a str,
+    shell: &'a mut Shell<'b>,
+    // How far back in the history the line being edited came from, None for a new line.
+    browsing: Option<usize>,
+    // The new line, saved while browsing the history.
+    draft: String,
+}
+
+impl<'a, 'b> LineEditor<'a, 'b> {
+    fn new(shell: &'a mut Shell<'b>, prompt: &'a str) -> Self {
+        LineEditor {
+            line: String::new(),
+            cursor: 0,
+            prompt: prompt,
+            shell: shell,
+            browsing: None,
+            draft: String::new(),
+        }
+    }
+
+    fn insert(&mut self, ch: char) {
+        self.line.insert(self.cursor, ch);
+        self.cursor += 1;
+        cprint!(self.shell.console, "{}", ch);
+        if self.cursor < self.line.len() {
+            self.redraw_tail();
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.line.remove(self.cursor);
+            self.move_left(1);
+            self.redraw_tail();
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.line.len() {
+            self.line.remove(self.cursor);
+            self.redraw_tail();
+        }
+    }
+
+    // Delete the word before the cursor, along with any spaces between it and the cursor.
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.line.as_bytes()[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.line.as_bytes()[start - 1] != b' ' {
+            start -= 1;
+        }
+        let count = self.cursor - start;
+        if count > 0 {
+            self.line = String::from(&self.line[..start]) + &self.line[self.cursor..];
+            self.cursor = start;
+            self.move_left(count);
+            self.redraw_tail();
+        }
+    }
+
+    fn clear_screen(&mut self) {
+        // ANSI ESC sequence to clear screen, the same one `clear` uses.
+        cprint!(self.shell.console, "\x1b[2J");
+        self.redraw();
+    }
+
+    fn left(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.move_left(1);
+        }
+    }
+
+    fn right(&mut self) {
+        if self.cursor < self.line.len() {
+            self.cursor += 1;
+            self.move_right(1);
+        }
+    }
+
+    fn home(&mut self) {
+        let count = self.cursor;
+        self.move_left(count);
+        self.cursor = 0;
+    }
+
+    fn end(&mut self) {
+        let count = self.line.len() - self.cursor;
+        self.move_right(count);
+        self.cursor = self.line.len();
+    }
+
+    fn up(&mut self) {
+        let back = self.browsing.map_or(0, |back| back + 1);
+        let entry = self.shell.history.get(back).map(String::from);
+        if let Some(entry) = entry {
+            if self.browsing.is_none() {
+                self.draft = self.line.clone();
+            }
+            self.browsing = Some(back);
+            self.replace(entry);
+        }
+    }
+
+    fn down(&mut self) {
+        match self.browsing {
+            Some(0) => {
+                self.browsing = None;
+                let draft = self.draft.clone();
+                self.replace(draft);
+            },
+            Some(back) => {
+                self.browsing = Some(back - 1);
+                let entry = self.shell.history.get(back - 1).map(String::from);
+                if let Some(entry) = entry {
+                    self.replace(entry);
+                }
+            },
+            None => {},
+        }
+    }
+
+    fn tab(&mut self) {
+        let before = String::from(&self.line[..self.cursor]);
+        let (partial, candidates) = complete::complete(&*self.shell, &before);
+        match candidates.len() {
+            0 => {},
+            1 => {
+                self.insert_str(&candidates[0][partial.len()..]);
+                self.insert(' ');
+            },
+            _ => {
+                let prefix = complete::common_prefix(&candidates);
+                if prefix.len() > partial.len() {
+                    self.insert_str(&prefix[partial.len()..]);
+                }
+                else {
+                    self.list(&candidates);
+                }
+            },
+        }
+    }
+
+    // Search back through the history for the newest line containing what is typed. Ctrl-R again
+    // finds the next older match. Returns the key that ended the search, leaving the match as the
+    // line being edited.
+    fn search(&mut self) -> Key {
+        let mut query = String::new();
+        let mut found = None;
+        let mut failed = false;
+        loop {
+            self.show_search(&query, found, failed);
+            let from = match read_key(self.shell.console) {
+                Key::Char(ch) => {
+                    query.push(ch);
+                    found.unwrap_or(0)
+                },
+                Key::Backspace => {
+                    query.pop();
+                    0
+                },
+                Key::Search => found.map_or(0, |back| back + 1),
+                key => {
+                    let entry = found.and_then(|back| self.shell.history.get(back)).map(String::from);
+                    if let Some(entry) = entry {
+                        self.line = entry;
+                        self.cursor = self.line.len();
+                        self.browsing = None;
+                    }
+                    self.redraw();
+                    return key;
+                },
+            };
+
+            if query.is_empty() {
+                found = None;
+                failed = false;
+                continue;
+            }
+            let history = &self.shell.history;
+            match (from..history.len()).find(|&back| history.get(back).map_or(false, |entry| entry.contains(&*query))) {
+                Some(back) => {
+                    found = Some(back);
+                    failed = false;
+                },
+                None => failed = true,
+            }
+        }
+    }
+
+    fn show_search(&mut self, query: &str, found: Option<usize>, failed: bool) {
+        let failed = if failed { "failed " } else { "" };
+        cprint!(self.shell.console, "\r({}reverse-i-search)'{}': ", failed, query);
+        if let Some(back) = found {
+            if let Some(entry) = self.shell.history.get(back) {
+                cprint!(self.shell.console, "{}", entry);
+            }
+        }
+        cprint!(self.shell.console, "\x1b[K");
+    }
+
+    // Insert completed text. Only the characters that could have been typed are kept.
+    fn insert_str(&mut self, string: &str) {
+        for ch in string.chars().filter(|&ch| ch >= ' ' && ch <= '~') {
+            self.insert(ch);
+        }
+    }
+
+    // Print the candidates below the line, then redraw the prompt and line under them.
+    fn list(&mut self, candidates: &[&str]) {
+        cprintln!(self.shell.console);
+        for candidate in candidates {
+            cprint!(self.shell.console, "{}  ", candidate);
+        }
+        cprintln!(self.shell.console);
+        self.redraw();
+    }
+
+    // Reprint the prompt and the whole line from the start of the terminal line, then put the
+    // cursor back where it was.
+    fn redraw(&mut self) {
+        cprint!(self.shell.console, "\r{}{}\x1b[K", self.prompt, self.line);
+        let count = self.line.len() - self.cursor;
+        self.move_left(count);
+    }
+
+    // Replace the whole line, leaving the cursor at the end.
+    fn replace(&mut self, line: String) {
+        self.home();
+        self.line = line;
+        self.redraw_tail();
+        self.end();
+    }
+
+    // Reprint the line from the cursor onward, clearing anything left over from before the edit,
+    // then put the cursor back where it was.
+    fn redraw_tail(&mut self) {
+        // ANSI ESC sequence to clear from the cursor to the end of the line.
+        cprint!(self.shell.console, "{}\x1b[K", &self.line[self.cursor..]);
+        let count = self.line.len() - self.cursor;
+        self.move_left(count);
+    }
+
+    fn move_left(&mut self, count: usize) {
+        if count > 0 {
+            cprint!(self.shell.console, "\x1b[{}D", count);
+        }
+    }
+
+    fn move_right(&mut self, count: usize) {
+        if count > 0 {
+            cprint!(self.shell.console, "\x1b[{}C", count);
+        }
+    }
+}
+
+/// Display the prompt and read a line from the console, echoing it as it is edited.
+///
+/// Ctrl-C cancels the line and Ctrl-D on an empty line ends the input, as does the console being
+/// closed. Otherwise Ctrl-D deletes the character under the cursor.
+pub fn edit_line(shell: &mut Shell, prompt: &str) -> Result<String, Interrupt> {
+    cprint!(shell.console, "{}", prompt);
+    let mut editor = LineEditor::new(shell, prompt);
+    loop {
+        let key = read_key(editor.shell.console);
+        match key {
+            Key::Char(ch) => editor.insert(ch),
+            Key::Enter => {
+                cprintln!(editor.shell.console);
+                return Ok(editor.line);
+            },
+            Key::Tab => editor.tab(),
+            Key::Backspace => editor.backspace(),
+            Key::Delete => editor.delete(),
+            Key::Up => editor.up(),
+            Key::Down => editor.down(),
+            Key::Left => editor.left(),
+            Key::Right => editor.right(),
+            Key::Home => editor.home(),
+            Key::End => editor.end(),
+            Key::Cancel => {
+                cprintln!(editor.shell.console, "^C");
+                return Err(Interrupt::Cancel);
+            },
+            Key::EndOfInput => {
+                if editor.line.is_empty() {
+                    cprintln!(editor.shell.console);
+                    return Err(Interrupt::EndOfInput);
+                }
+                editor.delete();
+            },
+            Key::ClearScreen => editor.clear_screen(),
+            Key::KillLine => editor.replace(String::new()),
+            Key::DeleteWord => editor.delete_word(),
+            // Enter runs the line found and Ctrl-C cancels it, any other key goes back to editing.
+            Key::Search => match editor.search() {
+                Key::Enter => {
+                    cprintln!(editor.shell.console);
+                    return Ok(editor.line);
+                },
+                Key::Cancel => {
+                    cprintln!(editor.shell.console, "^C");
+                    return Err(Interrupt::Cancel);
+                },
+                _ => {},
+            },
+            Key::Closed => {
+                cprintln!(editor.shell.console);
+                return Err(Interrupt::EndOfInput);
+            },
+            Key::Escape | Key::Unknown => {},
+        }
+    }
+}
+
+// Wait for the next character, or `None` if the console is closed.
+fn get_char(console: &mut Console) -> Option<char> {
+    loop {
+        if let Some(ch) = console.read_char() {
+            return Some(ch);
+        }
+        if console.is_closed() {
+            return None;
+        }
+    }
+}
+
+fn read_key(console: &mut Console) -> Key {
+    let ch = match get_char(console) {
+        Some(ch) => ch,
+        None => return Key::Closed,
+    };
+    match ch {
+        '\n' | '\r' => Key::Enter,
+        TAB => Key::Tab,
+        BACKSPACE | DEL => Key::Backspace,
+        CTRL_C => Key::Cancel,
+        CTRL_D => Key::EndOfInput,
+        CTRL_L => Key::ClearScreen,
+        CTRL_U => Key::KillLine,
+        CTRL_W => Key::DeleteWord,
+        CTRL_R => Key::Search,
+        ESC => read_escape(console),
+        ch if ch >= ' ' && ch <= '~' => Key::Char(ch),
+        _ => Key::Unknown,
+    }
+}
+
+// Wait up to `ms` milliseconds for a character.
+fn poll_char(console: &mut Console, ms: usize) -> Option<char> {
+    let mut waited = 0;
+    loop {
+        if let Some(ch) = console.read_char() {
+            return Some(ch);
+        }
+        if waited == ms || console.is_closed() {
+            return None;
+        }
+        delay_ms(1);
+        waited += 1;
+    }
+}
+
+// Decode the rest of an escape sequence after the ESC character.
+//
+// Terminals send either `ESC [ <params> <final>` or `ESC O <final>` for the keys we care about,
+// where Home, End and Delete may also be sent as `ESC [ <n> ~`. An ESC with nothing after it is the
+// Esc key itself.
+fn read_escape(console: &mut Console) -> Key {
+    match poll_char(console, ESC_TIMEOUT_MS) {
+        Some('[') => {
+            let mut param: u32 = 0;
+            loop {
+                match get_char(console) {
+                    // Saturate so a long run of digits from line noise can't overflow.
+                    Some(ch) if ch.is_digit(10) => param = param.saturating_mul(10).saturating_add(ch.to_digit(10).unwrap_or(0)),
+                    Some(';') => param = 0,
+                    Some('~') => return match param {
+                        1 | 7 => Key::Home,
+                        3 => Key::Delete,
+                        4 | 8 => Key::End,
+                        _ => Key::Unknown,
+                    },
+                    Some(ch) => return final_key(ch),
+                    None => return Key::Closed,
+                }
+            }
+        },
+        Some('O') => match get_char(console) {
+            Some(ch) => final_key(ch),
+            None => Key::Closed,
+        },
+        Some(_) => Key::Unknown,
+        None => Key::Escape,
+    }
+}
+
+fn final_key(ch: char) -> Key {
+    match ch {
+        'A' => Key::Up,
+        'B' => Key::Down,
+        'C' => Key::Right,
+        'D' => Key::Left,
+        'H' => Key::Home,
+        'F' => Key::End,
+        _ => Key::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, Interrupt, HISTORY_SIZE, edit_line};
+    use kernel::collections::String;
+    use super::super::{Shell, Buffer};
+    use std::string::ToString;
+
+    // Type the keys into the line editor, returning each line read until one isn't, followed by
+    // why it wasn't. Lines are added to the history as the shell does.
+    fn edit(keys: &str) -> (String, &'static str) {
+        let mut console = Buffer::new(keys);
+        let mut shell = Shell::new(&mut console);
+        let mut lines = String::new();
+        loop {
+            match edit_line(&mut shell, "> ") {
+                Ok(line) => {
+                    shell.history.push(&line);
+                    lines.push_str(&line);
+                    lines.push('|');
+                },
+                Err(Interrupt::Cancel) => return (lines, "cancel"),
+                Err(Interrupt::EndOfInput) => return (lines, "end"),
+            }
+        }
+    }
+
+    fn lines(keys: &str) -> String {
+        edit(keys).0
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        assert_eq!(lines("abd\x1b[DX\x1b[C\x08c\r"), "abXc|");
+        assert_eq!(lines("bc\x1b[Ha\x1b[Fd\r"), "abcd|");
+        assert_eq!(lines("xabc\x1b[1~\x1b[3~\x1b[4~!\r"), "abc!|");
+        assert_eq!(lines("ab\x1bOD\x7f\x7f\r"), "b|");
+    }
+
+    #[test]
+    fn ignores_line_noise() {
+        assert_eq!(lines("a\x01\x1b[5~\x1bZb\r"), "ab|");
+        // A run of digits that looks like a huge escape sequence parameter.
+        assert_eq!(lines("a\x1b[99999999999999999999Cb\r"), "ab|");
+    }
+
+    #[test]
+    fn control_keys() {
+        assert_eq!(lines("junk\x15ok\r"), "ok|");
+        assert_eq!(lines("one two  \x17three\r"), "one three|");
+        assert_eq!(lines("ab\x0cc\r"), "abc|");
+        assert_eq!(edit("abc\x03"), (String::new(), "cancel"));
+        assert_eq!(edit("a\r\x04"), (String::from("a|"), "end"));
+    }
+
+    #[test]
+    fn browses_history() {
+        assert_eq!(lines("one\rtwo\r\x1b[A\x1b[A\r"), "one|two|one|");
+        assert_eq!(lines("one\rdraft\x1b[A\x1b[B!\r"), "one|draft!|");
+        assert_eq!(lines("one\r\x1b[A\x1b[A\x1b[B\x1b[B\r"), "one||");
+    }
+
+    #[test]
+    fn searches_history() {
+        assert_eq!(lines("apple\rbanana\rapricot\r\x12ap\x12\r"), "apple|banana|apricot|apple|");
+        // Any other key goes back to editing the line found.
+        assert_eq!(lines("banana\r\x12nan\x1bZ\x08X\r"), "banana|bananX|");
+        assert_eq!(edit("one\r\x12on\x03"), (String::from("one|"), "cancel"));
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = History::new();
+        for i in 0..HISTORY_SIZE + 2 {
+            history.push(&i.to_string());
+        }
+        history.push("  ");
+        history.push(&(HISTORY_SIZE + 1).to_string());
+        assert_eq!(history.len(), HISTORY_SIZE);
+        assert_eq!(history.get(0), Some(&*(HISTORY_SIZE + 1).to_string()));
+        assert_eq!(history.get(HISTORY_SIZE - 1), Some("2"));
+        assert_eq!(history.get(HISTORY_SIZE), None);
+    }
+
+    #[test]
+    fn ends_when_input_runs_out() {
+        assert_eq!(edit("half a line"), (String::new(), "end"));
+        assert_eq!(edit("a\x1b["), (String::new(), "end"));
+    }
+}
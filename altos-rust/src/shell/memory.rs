@@ -0,0 +1,335 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Commands for reading and writing memory directly. Every access is checked against the memory
+// map first, so a mistyped address is an error rather than a HardFault.
+
+use core::cmp;
+use core::fmt::{self, Display};
+use core::ptr;
+use super::{Shell, Console};
+use super::command::{Status, SUCCESS, FAILURE, USAGE};
+use super::eval;
+
+pub const PEEK_USAGE: &'static str = "peek <addr> [count] [--width 8|16|32]";
+pub const POKE_USAGE: &'static str = "poke <addr> <value> [--width 8|16|32]";
+pub const HEXDUMP_USAGE: &'static str = "hexdump <addr> <len> | hexdump .data|.bss|heap|stack [len]";
+
+// Bytes shown on each line of a hexdump.
+const HEXDUMP_ROW: usize = 16;
+
+// Symbols defined by cortex_m0.ld. Only their addresses mean anything.
+extern "C" {
+    static _flash_start: u8;
+    static _flash_end: u8;
+    static _sdata: u8;
+    static _edata: u8;
+    static _sbss: u8;
+    static _ebss: u8;
+    static _heap_start: u8;
+    static _heap_end: u8;
+    static _stack_start: u8;
+    static _stack_end: u8;
+}
+
+/// A range of addresses that can be accessed.
+pub struct Region {
+    pub name: &'static str,
+    pub start: usize,
+    /// The address just past the end of the region.
+    pub end: usize,
+    pub writable: bool,
+}
+
+/// The peripherals of the STM32F042, from the memory map in the reference manual (RM0091). Only
+/// these blocks are listed because the reserved space between them faults when accessed, as does
+/// most of the Cortex-M0's private peripheral bus outside of the system control space.
+pub static PERIPHERALS: [Region; 34] = [
+    Region { name: "TIM2", start: 0x4000_0000, end: 0x4000_0400, writable: true },
+    Region { name: "TIM3", start: 0x4000_0400, end: 0x4000_0800, writable: true },
+    Region { name: "TIM14", start: 0x4000_2000, end: 0x4000_2400, writable: true },
+    Region { name: "RTC", start: 0x4000_2800, end: 0x4000_2C00, writable: true },
+    Region { name: "WWDG", start: 0x4000_2C00, end: 0x4000_3000, writable: true },
+    Region { name: "IWDG", start: 0x4000_3000, end: 0x4000_3400, writable: true },
+    Region { name: "SPI2", start: 0x4000_3800, end: 0x4000_3C00, writable: true },
+    Region { name: "USART2", start: 0x4000_4400, end: 0x4000_4800, writable: true },
+    Region { name: "I2C1", start: 0x4000_5400, end: 0x4000_5800, writable: true },
+    Region { name: "USB", start: 0x4000_5C00, end: 0x4000_6000, writable: true },
+    Region { name: "USB SRAM", start: 0x4000_6000, end: 0x4000_6400, writable: true },
+    Region { name: "CAN", start: 0x4000_6400, end: 0x4000_6800, writable: true },
+    Region { name: "CRS", start: 0x4000_6C00, end: 0x4000_7000, writable: true },
+    Region { name: "PWR", start: 0x4000_7000, end: 0x4000_7400, writable: true },
+    Region { name: "CEC", start: 0x4000_7800, end: 0x4000_7C00, writable: true },
+    Region { name: "SYSCFG", start: 0x4001_0000, end: 0x4001_0400, writable: true },
+    Region { name: "EXTI", start: 0x4001_0400, end: 0x4001_0800, writable: true },
+    Region { name: "ADC", start: 0x4001_2400, end: 0x4001_2800, writable: true },
+    Region { name: "TIM1", start: 0x4001_2C00, end: 0x4001_3000, writable: true },
+    Region { name: "SPI1", start: 0x4001_3000, end: 0x4001_3400, writable: true },
+    Region { name: "USART1", start: 0x4001_3800, end: 0x4001_3C00, writable: true },
+    Region { name: "TIM16", start: 0x4001_4400, end: 0x4001_4800, writable: true },
+    Region { name: "TIM17", start: 0x4001_4800, end: 0x4001_4C00, writable: true },
+    Region { name: "DBGMCU", start: 0x4001_5800, end: 0x4001_5C00, writable: true },
+    Region { name: "DMA", start: 0x4002_0000, end: 0x4002_0400, writable: true },
+    Region { name: "RCC", start: 0x4002_1000, end: 0x4002_1400, writable: true },
+    Region { name: "FLASH interface", start: 0x4002_2000, end: 0x4002_2400, writable: true },
+    Region { name: "CRC", start: 0x4002_3000, end: 0x4002_3400, writable: true },
+    Region { name: "TSC", start: 0x4002_4000, end: 0x4002_4400, writable: true },
+    Region { name: "GPIOA", start: 0x4800_0000, end: 0x4800_0400, writable: true },
+    Region { name: "GPIOB", start: 0x4800_0400, end: 0x4800_0800, writable: true },
+    Region { name: "GPIOC", start: 0x4800_0800, end: 0x4800_0C00, writable: true },
+    Region { name: "GPIOF", start: 0x4800_1400, end: 0x4800_1800, writable: true },
+    Region { name: "System control space", start: 0xE000_E000, end: 0xE000_F000, writable: true },
+];
+
+/// FLASH and RAM, with their bounds taken from cortex_m0.ld.
+pub fn memory() -> [Region; 2] {
+    unsafe {
+        [
+            Region { name: "FLASH", start: address(&_flash_start), end: address(&_flash_end), writable: false },
+            // `.data` is the first thing in RAM and the stack is the last.
+            Region { name: "RAM", start: address(&_sdata), end: address(&_stack_start), writable: true },
+        ]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum MemoryError {
+    /// Part of the range isn't in any region.
+    Unmapped,
+    /// The range is in a region that can't be written.
+    ReadOnly(&'static str),
+    /// The address isn't a multiple of the access width.
+    Unaligned,
+}
+
+impl Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            MemoryError::Unmapped => "address is outside of the memory map",
+            MemoryError::ReadOnly(name) => return write!(f, "{} is read-only", name),
+            MemoryError::Unaligned => "address must be a multiple of the width",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Check that `len` bytes starting at `addr` can be accessed `width` bytes at a time.
+pub fn check(addr: usize, len: usize, width: usize, write: bool) -> Result<(), MemoryError> {
+    if addr % width != 0 {
+        return Err(MemoryError::Unaligned);
+    }
+    let end = match addr.checked_add(len) {
+        Some(end) => end,
+        None => return Err(MemoryError::Unmapped),
+    };
+    let memory = memory();
+    match memory.iter().chain(PERIPHERALS.iter()).find(|region| addr >= region.start && end <= region.end) {
+        Some(region) if write && !region.writable => Err(MemoryError::ReadOnly(region.name)),
+        Some(_) => Ok(()),
+        None => Err(MemoryError::Unmapped),
+    }
+}
+
+/// Parse an address, in any format `eval` accepts.
+pub fn parse_address(string: &str) -> Option<usize> {
+    eval::parse_number(string).map(|addr| addr as usize)
+}
+
+pub fn peek(shell: &mut Shell, args: &[&str]) -> Status {
+    let (addr, count, width) = match parse_args(args) {
+        Some((addr, count, width)) => (addr, count.unwrap_or(1), width),
+        None => {
+            cprintln!(shell.console, "Usage: {}", PEEK_USAGE);
+            return USAGE;
+        },
+    };
+    let len = match count.checked_mul(width) {
+        Some(len) => len,
+        None => {
+            cprintln!(shell.console, "Can't read {:#x}: {}", addr, MemoryError::Unmapped);
+            return FAILURE;
+        },
+    };
+    if let Err(err) = check(addr, len, width, false) {
+        cprintln!(shell.console, "Can't read {:#x}: {}", addr, err);
+        return FAILURE;
+    }
+
+    for i in 0..count {
+        let addr = addr + i * width;
+        let value = unsafe { read(addr, width) };
+        cprintln!(shell.console, "{:#010x}: {:#0digits$x}", addr, value, digits = width * 2 + 2);
+    }
+    SUCCESS
+}
+
+pub fn poke(shell: &mut Shell, args: &[&str]) -> Status {
+    // The value is in the place of peek's count.
+    let (addr, value, width) = match parse_args(args) {
+        Some((addr, Some(value), width)) => (addr, value, width),
+        _ => {
+            cprintln!(shell.console, "Usage: {}", POKE_USAGE);
+            return USAGE;
+        },
+    };
+    if width < 4 && value >> (width * 8) != 0 {
+        cprintln!(shell.console, "{:#x} doesn't fit in {} bits", value, width * 8);
+        return USAGE;
+    }
+    if let Err(err) = check(addr, width, width, true) {
+        cprintln!(shell.console, "Can't write {:#x}: {}", addr, err);
+        return FAILURE;
+    }
+
+    unsafe { write(addr, width, value) };
+    SUCCESS
+}
+
+pub fn hexdump(shell: &mut Shell, args: &[&str]) -> Status {
+    if args.len() == 0 || args.len() > 2 {
+        cprintln!(shell.console, "Usage: {}", HEXDUMP_USAGE);
+        return USAGE;
+    }
+    let len = match args.get(1).map(|len| eval::parse_number(len)) {
+        Some(Some(len)) => Some(len as usize),
+        Some(None) => {
+            cprintln!(shell.console, "Usage: {}", HEXDUMP_USAGE);
+            return USAGE;
+        },
+        None => None,
+    };
+    let (start, len) = match (symbol_region(args[0]), parse_address(args[0]), len) {
+        // A length given with a region only dumps the start of it.
+        (Some((start, end)), _, len) => (start, len.map_or(end - start, |len| cmp::min(len, end - start))),
+        (None, Some(addr), Some(len)) => (addr, len),
+        _ => {
+            cprintln!(shell.console, "Usage: {}", HEXDUMP_USAGE);
+            return USAGE;
+        },
+    };
+    if let Err(err) = check(start, len, 1, false) {
+        cprintln!(shell.console, "Can't read {:#x}: {}", start, err);
+        return FAILURE;
+    }
+
+    let mut row = [0; HEXDUMP_ROW];
+    let mut offset = 0;
+    while offset < len {
+        let count = cmp::min(len - offset, HEXDUMP_ROW);
+        for i in 0..count {
+            row[i] = unsafe { read(start + offset + i, 1) as u8 };
+        }
+        write_row(shell.console, start + offset, &row[..count]);
+        offset += count;
+    }
+    SUCCESS
+}
+
+/// The size of the heap in bytes, which every task's stack is allocated from.
+pub fn heap_size() -> usize {
+    symbol_region("heap").map_or(0, |(start, end)| end - start)
+}
+
+// The start and end of a region named after a section of the linker script.
+fn symbol_region(name: &str) -> Option<(usize, usize)> {
+    let (start, end) = unsafe {
+        match name {
+            ".data" => (&_sdata, &_edata),
+            ".bss" => (&_sbss, &_ebss),
+            "heap" => (&_heap_start, &_heap_end),
+            // The stack grows down from `_stack_start`.
+            "stack" => (&_stack_end, &_stack_start),
+            _ => return None,
+        }
+    };
+    Some((address(start), address(end)))
+}
+
+fn address(symbol: &u8) -> usize {
+    symbol as *const u8 as usize
+}
+
+// Print a line of a hexdump in the same format as `xxd`: the address, the bytes in pairs, then the
+// bytes as ASCII with a `.` for anything that isn't printable.
+fn write_row(console: &mut Console, addr: usize, bytes: &[u8]) {
+    cprint!(console, "{:08x}:", addr);
+    for i in 0..HEXDUMP_ROW {
+        if i % 2 == 0 {
+            cprint!(console, " ");
+        }
+        match bytes.get(i) {
+            Some(byte) => cprint!(console, "{:02x}", byte),
+            None => cprint!(console, "  "),
+        }
+    }
+    cprint!(console, "  ");
+    for &byte in bytes {
+        let ch = if byte >= b' ' && byte <= b'~' { byte as char } else { '.' };
+        cprint!(console, "{}", ch);
+    }
+    cprintln!(console);
+}
+
+// Parse `<addr> [number] [--width 8|16|32]`, returning the width in bytes.
+fn parse_args(args: &[&str]) -> Option<(usize, Option<usize>, usize)> {
+    let mut addr = None;
+    let mut number = None;
+    let mut width = 4;
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        if arg == "--width" {
+            width = match args.next().map(|width| *width) {
+                Some("8") => 1,
+                Some("16") => 2,
+                Some("32") => 4,
+                _ => return None,
+            };
+        }
+        else if addr.is_none() {
+            addr = parse_address(arg);
+            if addr.is_none() {
+                return None;
+            }
+        }
+        else if number.is_none() {
+            number = eval::parse_number(arg).map(|number| number as usize);
+            if number.is_none() {
+                return None;
+            }
+        }
+        else {
+            return None;
+        }
+    }
+    addr.map(|addr| (addr, number, width))
+}
+
+unsafe fn read(addr: usize, width: usize) -> usize {
+    match width {
+        1 => ptr::read_volatile(addr as *const u8) as usize,
+        2 => ptr::read_volatile(addr as *const u16) as usize,
+        _ => ptr::read_volatile(addr as *const u32) as usize,
+    }
+}
+
+unsafe fn write(addr: usize, width: usize, value: usize) {
+    match width {
+        1 => ptr::write_volatile(addr as *mut u8, value as u8),
+        2 => ptr::write_volatile(addr as *mut u16, value as u16),
+        _ => ptr::write_volatile(addr as *mut u32, value as u32),
+    }
+}
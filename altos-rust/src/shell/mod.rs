@@ -0,0 +1,760 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The shell core (line editing, splitting lines into commands, variables, aliases, scripts and
+// the portable builtins) only talks to a `Console`, so it also builds for the host where it can be
+// tested with `cargo test`. Jobs, tasks and the commands in `board` are only built for the board.
+
+#[cfg(target_arch = "arm")]
+use cortex_m0::kernel;
+#[cfg(target_arch = "arm")]
+use kernel::task::args::Args;
+use kernel::collections::{Vec, String};
+use core::fmt::{self, Display, Write};
+use core::iter::Peekable;
+use core::str::Chars;
+
+pub use self::command::{Command, RegisterError, Status, SUCCESS, FAILURE, USAGE, NOT_FOUND, INTERRUPTED, register};
+pub use self::console::{Console, Buffer};
+#[cfg(target_arch = "arm")]
+pub use self::console::{Uart, UartOutput};
+#[cfg(target_arch = "arm")]
+pub use self::gpio::{Pin, claim_pin};
+#[cfg(target_arch = "arm")]
+pub use self::spawn::{Spawnable, register_task};
+use self::alias::Aliases;
+use self::env::Environment;
+use self::eval::Variables;
+#[cfg(target_arch = "arm")]
+use self::jobs::Jobs;
+use self::line::{History, Interrupt};
+use self::script::Scripts;
+
+// Like `print!` and `println!`, but write to the given console instead of the UART.
+macro_rules! cprint {
+    ($console:expr, $($arg:tt)*) => ({
+        use core::fmt::Write;
+        let _ = write!($console, $($arg)*);
+    });
+}
+
+macro_rules! cprintln {
+    ($console:expr) => (cprint!($console, "\n"));
+    ($console:expr, $fmt:expr) => (cprint!($console, concat!($fmt, "\n")));
+    ($console:expr, $fmt:expr, $($arg:tt)*) => (cprint!($console, concat!($fmt, "\n"), $($arg)*));
+}
+
+mod alias;
+#[cfg(target_arch = "arm")]
+mod board;
+mod builtins;
+mod command;
+mod complete;
+mod console;
+mod env;
+mod eval;
+#[cfg(target_arch = "arm")]
+mod gpio;
+#[cfg(target_arch = "arm")]
+mod jobs;
+mod line;
+#[cfg(target_arch = "arm")]
+mod memory;
+mod script;
+#[cfg(target_arch = "arm")]
+mod spawn;
+mod table;
+
+const PROMPT: &'static str = " > ";
+
+// How a command is joined to the one before it.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Separator {
+    // `;`, always run the command.
+    Sequence,
+    // `&&`, run the command if the last one succeeded.
+    And,
+    // `||`, run the command if the last one failed.
+    Or,
+    // `&` at the end of a command, run it in the background and carry on.
+    Background,
+}
+
+// A word as it was typed, with its variables still to be expanded.
+struct Word {
+    parts: Vec<Part>,
+    // Whether the word has any text or quotes of its own. A word that is only variables isn't a
+    // word at all if they expand to nothing.
+    literal: bool,
+}
+
+enum Part {
+    Text(String),
+    // The name of a variable, including `?`.
+    Variable(String),
+}
+
+impl Word {
+    fn new() -> Self {
+        Word {
+            parts: Vec::new(),
+            literal: false,
+        }
+    }
+
+    fn push(&mut self, ch: char) {
+        self.literal = true;
+        if let Some(&mut Part::Text(ref mut text)) = self.parts.last_mut() {
+            text.push(ch);
+            return;
+        }
+        let mut text = String::new();
+        text.push(ch);
+        self.parts.push(Part::Text(text));
+    }
+}
+
+// The words of a command and the separator after it, `None` at the end of the line.
+type ParsedCommand = (Vec<Word>, Option<Separator>);
+
+enum ReadError {
+    UnclosedString,
+    UnclosedVariable,
+    UnknownEscape(char),
+    InvalidHexEscape,
+    TrailingBackslash,
+    AliasLoop,
+    CompoundAlias,
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            ReadError::UnclosedString => "unclosed string found",
+            ReadError::UnclosedVariable => "unclosed ${ found",
+            ReadError::UnknownEscape(ch) => return write!(f, "unknown escape sequence \\{}", ch),
+            ReadError::InvalidHexEscape => "\\x must be followed by two hex digits from 00 to 7f",
+            ReadError::TrailingBackslash => "line ends with a backslash",
+            ReadError::AliasLoop => "alias expands back to itself",
+            ReadError::CompoundAlias => "aliases can only contain one command",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// State kept by the shell between commands.
+pub struct Shell<'a> {
+    console: &'a mut Console,
+    history: History,
+    variables: Variables,
+    env: Environment,
+    scripts: Scripts,
+    aliases: Aliases,
+    #[cfg(target_arch = "arm")]
+    jobs: Jobs,
+    // How many scripts are running inside each other.
+    run_depth: usize,
+    // The exit status of the last command run, expanded by `$?`.
+    status: Status,
+    // Whether to show a non-zero status in the prompt.
+    show_status: bool,
+}
+
+impl<'a> Shell<'a> {
+    /// Create a shell attached to a console.
+    pub fn new(console: &'a mut Console) -> Self {
+        Shell {
+            console: console,
+            history: History::new(),
+            variables: Variables::new(),
+            env: Environment::new(),
+            scripts: Scripts::new(),
+            aliases: Aliases::new(),
+            #[cfg(target_arch = "arm")]
+            jobs: Jobs::new(),
+            run_depth: 0,
+            status: SUCCESS,
+            show_status: false,
+        }
+    }
+
+    /// The console the shell is attached to.
+    ///
+    /// Commands registered with `register` should write their output here rather than using
+    /// `print!`, so it goes wherever the shell's output goes, like a job's console or a `Buffer`.
+    pub fn console(&mut self) -> &mut Console {
+        self.console
+    }
+
+    /// Look up a variable for `$NAME` expansion.
+    fn variable(&self, name: &str) -> Option<String> {
+        match name {
+            "PROMPT" => Some(String::from(PROMPT)),
+            "?" => {
+                let mut status = String::new();
+                let _ = write!(status, "{}", self.status);
+                Some(status)
+            },
+            "UPTIME" => {
+                let hms = builtins::get_uptime();
+                let mut uptime = String::new();
+                let _ = write!(uptime, "{:02}:{:02}:{:02}", hms.0, hms.1, hms.2);
+                Some(uptime)
+            },
+            _ => self.env.get(name).map(String::from),
+        }
+    }
+
+    /// Prompt for a line on the console and run it.
+    ///
+    /// Returns false without running anything once the input has ended, either from Ctrl-D on an
+    /// empty line or the console being closed, at which point the shell should exit.
+    pub fn read_and_execute(&mut self) -> bool {
+        let mut prompt = String::new();
+        if self.show_status && self.status != SUCCESS {
+            let _ = write!(prompt, "[{}]", self.status);
+        }
+        prompt.push_str(PROMPT);
+        let line = match line::edit_line(self, &prompt) {
+            Ok(line) => line,
+            Err(Interrupt::Cancel) => {
+                self.status = INTERRUPTED;
+                return true;
+            },
+            Err(Interrupt::EndOfInput) => return false,
+        };
+        self.history.push(&line);
+        self.run_line(&line);
+        true
+    }
+
+    /// Run each of the commands on a line, returning the status of the last one run.
+    ///
+    /// The whole line is split into commands first, so nothing runs if any of it can't be read.
+    /// Variables are expanded as each command runs, so `$?` is the status of the command before it.
+    pub fn run_line(&mut self, line: &str) -> Status {
+        let commands = match parse_line(line) {
+            Ok(commands) => commands,
+            Err(err) => {
+                cprintln!(self.console, "Error: {}", err);
+                self.status = FAILURE;
+                return self.status;
+            },
+        };
+        let mut run = true;
+        for (words, separator) in commands {
+            let words = if run { self.expand(&words) } else { Vec::new() };
+            if !words.is_empty() {
+                self.status = match separator {
+                    Some(Separator::Background) => self.start_job(&words),
+                    _ => self.run_words(&words),
+                };
+            }
+            run = match separator {
+                Some(Separator::Sequence) | Some(Separator::Background) => true,
+                Some(Separator::And) => self.status == SUCCESS,
+                Some(Separator::Or) => self.status != SUCCESS,
+                None => false,
+            };
+        }
+        self.status
+    }
+
+    // Expand the variables in a command's words.
+    fn expand(&self, words: &[Word]) -> Vec<String> {
+        let mut expanded = Vec::new();
+        for word in words {
+            let mut string = String::new();
+            for part in &word.parts {
+                match *part {
+                    Part::Text(ref text) => string.push_str(text),
+                    Part::Variable(ref name) => if let Some(value) = self.variable(name) {
+                        string.push_str(&value);
+                    },
+                }
+            }
+            if word.literal || !string.is_empty() {
+                expanded.push(string);
+            }
+        }
+        expanded
+    }
+
+    fn run_words(&mut self, words: &[String]) -> Status {
+        let words = match self.expand_aliases(words) {
+            Ok(words) => words,
+            Err(err) => {
+                cprintln!(self.console, "Error: {}", err);
+                return FAILURE;
+            },
+        };
+        let words: Vec<&str> = words.iter().map(|s| s.as_ref()).collect();
+        match words.split_first() {
+            Some((name, args)) => self.execute(name, args),
+            None => SUCCESS,
+        }
+    }
+
+    // Run a command as a job in its own task. Aliases are expanded here since the job's shell
+    // doesn't have them.
+    #[cfg(target_arch = "arm")]
+    fn start_job(&mut self, words: &[String]) -> Status {
+        let words = match self.expand_aliases(words) {
+            Ok(words) => words,
+            Err(err) => {
+                cprintln!(self.console, "Error: {}", err);
+                return FAILURE;
+            },
+        };
+        if words.is_empty() {
+            return SUCCESS;
+        }
+        let name = match command::find(&words[0]) {
+            Some(command) => command.name,
+            None => {
+                cprintln!(self.console, "Unknown command: '{}'", words[0]);
+                return NOT_FOUND;
+            },
+        };
+
+        let id = match self.jobs.start(name, words) {
+            Ok(id) => id,
+            Err(err) => {
+                cprintln!(self.console, "Can't start job: {}", err);
+                return FAILURE;
+            },
+        };
+        if let Some(job) = self.jobs.get(id) {
+            if let Ok(tid) = job.handle.tid() {
+                cprintln!(self.console, "[{}] {}", id, tid);
+            }
+        }
+        SUCCESS
+    }
+
+    // There are no tasks to run jobs in on the host.
+    #[cfg(not(target_arch = "arm"))]
+    fn start_job(&mut self, _words: &[String]) -> Status {
+        cprintln!(self.console, "Error: background jobs are only available on the board");
+        FAILURE
+    }
+
+    // Replace the first word with the words of its alias until it is no longer an alias. An alias
+    // that comes up again is left alone if there is a command with the same name, so an alias can
+    // add arguments to a command, otherwise it is an error.
+    fn expand_aliases(&self, words: &[String]) -> Result<Vec<String>, ReadError> {
+        let mut words = words.to_vec();
+        let mut expanded: Vec<String> = Vec::new();
+        loop {
+            let value = match words.first().and_then(|name| self.aliases.get(name)) {
+                Some(value) => value,
+                None => return Ok(words),
+            };
+            if expanded.contains(&words[0]) {
+                if command::find(&words[0]).is_some() {
+                    return Ok(words);
+                }
+                return Err(ReadError::AliasLoop);
+            }
+            let commands = parse_line(value)?;
+            if commands.len() > 1 {
+                return Err(ReadError::CompoundAlias);
+            }
+            let mut alias_words = self.expand(&commands[0].0);
+            expanded.push(words[0].clone());
+            alias_words.extend(words.into_iter().skip(1));
+            words = alias_words;
+        }
+    }
+
+    fn execute(&mut self, name: &str, args: &[&str]) -> Status {
+        match command::find(name) {
+            Some(command) => (command.handler)(self, args),
+            None => {
+                cprintln!(self.console, "Unknown command: '{}'", name);
+                NOT_FOUND
+            },
+        }
+    }
+}
+
+#[cfg(target_arch = "arm")]
+pub fn shell(_args: &mut Args) {
+    let mut uart = Uart;
+    let mut shell = Shell::new(&mut uart);
+    while shell.read_and_execute() {}
+    // Ctrl-D on an empty line exits the shell like `exit`.
+    kernel::syscall::exit();
+}
+
+// Split a whole line into its commands.
+fn parse_line(line: &str) -> Result<Vec<ParsedCommand>, ReadError> {
+    let mut chars = line.chars().peekable();
+    let mut commands = Vec::new();
+    loop {
+        let command = read_command(&mut chars)?;
+        let end = command.1.is_none();
+        commands.push(command);
+        if end {
+            return Ok(commands);
+        }
+    }
+}
+
+// Split the next command on a line into words, mostly following the POSIX shell rules, stopping
+// after the separator that ends it. The separator is `None` at the end of the line.
+//
+// Spaces inside quotes don't split words, and quoted strings next to each other or to unquoted
+// text are joined into one word. Nothing is special inside single quotes. Inside double quotes and
+// unquoted text, variables are marked to be expanded and backslash escapes are replaced. An empty
+// pair of quotes is an empty word. Unquoted `;`, `&&` and `||` separate commands, and so does a `&`
+// at the end of a command. Any other `&` or `|` is kept as part of a word so they can still be used
+// in `eval`.
+fn read_command(chars: &mut Peekable<Chars>) -> Result<ParsedCommand, ReadError> {
+    let mut words = Vec::new();
+    let mut word = Word::new();
+    // Whether there is a word being built, which may still be empty if it was only quotes or
+    // variables.
+    let mut in_word = false;
+    let mut in_string = false;
+    let mut separator = None;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' if !in_string => {
+                in_word = true;
+                word.literal = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err(ReadError::UnclosedString),
+                    }
+                }
+            },
+            '"' => {
+                in_word = true;
+                word.literal = true;
+                in_string = !in_string;
+            },
+            ' ' if !in_string => {
+                if in_word {
+                    words.push(word);
+                    word = Word::new();
+                    in_word = false;
+                }
+            },
+            ';' | '&' | '|' if !in_string => {
+                separator = match ch {
+                    ';' => Some(Separator::Sequence),
+                    '&' if chars.peek() == Some(&'&') => {
+                        chars.next();
+                        Some(Separator::And)
+                    },
+                    '&' if ends_command(chars) => Some(Separator::Background),
+                    '|' if chars.peek() == Some(&'|') => {
+                        chars.next();
+                        Some(Separator::Or)
+                    },
+                    _ => None,
+                };
+                if separator.is_some() {
+                    break;
+                }
+                in_word = true;
+                word.push(ch);
+            },
+            '\\' => {
+                in_word = true;
+                word.push(read_escape(chars)?);
+            },
+            '$' => {
+                in_word = true;
+                match read_variable(chars)? {
+                    Some(name) => word.parts.push(Part::Variable(name)),
+                    None => word.push('$'),
+                }
+            },
+            _ => {
+                in_word = true;
+                word.push(ch);
+            },
+        }
+    }
+    if in_string {
+        return Err(ReadError::UnclosedString);
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok((words, separator))
+}
+
+// Returns true if there is nothing but spaces before the end of the line or the next `;`.
+fn ends_command(chars: &Peekable<Chars>) -> bool {
+    let mut rest = chars.clone();
+    rest.find(|&ch| ch != ' ').map_or(true, |ch| ch == ';')
+}
+
+// Read the rest of a backslash escape sequence after the backslash.
+fn read_escape(chars: &mut Peekable<Chars>) -> Result<char, ReadError> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        // Only ASCII, since characters are written to the console UTF-8 encoded, so anything above
+        // 0x7f would be sent as two bytes rather than the byte asked for.
+        Some('x') => {
+            let high = chars.next().and_then(|ch| ch.to_digit(16));
+            let low = chars.next().and_then(|ch| ch.to_digit(16));
+            match (high, low) {
+                (Some(high), Some(low)) if high < 8 => Ok(((high << 4 | low) as u8) as char),
+                _ => Err(ReadError::InvalidHexEscape),
+            }
+        },
+        Some(ch @ '\\') | Some(ch @ '"') | Some(ch @ '\'') | Some(ch @ '$') | Some(ch @ ' ') => Ok(ch),
+        Some(ch) => Err(ReadError::UnknownEscape(ch)),
+        None => Err(ReadError::TrailingBackslash),
+    }
+}
+
+// Read the name of the variable in `$NAME`, `${NAME}` or `$?`, after the `$` has been read. Returns
+// `None` for a `$` that isn't followed by a name, which is kept as is.
+fn read_variable(chars: &mut Peekable<Chars>) -> Result<Option<String>, ReadError> {
+    let mut name = String::new();
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(ch) => name.push(ch),
+                None => return Err(ReadError::UnclosedVariable),
+            }
+        }
+    }
+    else if chars.peek() == Some(&'?') {
+        chars.next();
+        name.push('?');
+    }
+    else {
+        while let Some(&ch) = chars.peek() {
+            if !eval::is_identifier_char(ch) {
+                break;
+            }
+            name.push(ch);
+            chars.next();
+        }
+        if name.is_empty() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Shell, Buffer, Separator, Command, Status, SUCCESS, parse_line, register};
+    use kernel::collections::{Vec, String};
+    use core::fmt::Write;
+    use std::string::ToString;
+
+    // Split a whole line into commands, with $N set to 42. Errors are returned as their message.
+    fn commands(line: &str) -> Result<Vec<(Vec<String>, Option<Separator>)>, String> {
+        let mut console = Buffer::new("");
+        let mut shell = Shell::new(&mut console);
+        let _ = shell.env.set("N", "42");
+        let commands = parse_line(line).map_err(|err| err.to_string())?;
+        Ok(commands.iter().map(|&(ref words, separator)| (shell.expand(words), separator)).collect())
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        commands(line).unwrap().remove(0).0
+    }
+
+    // Type the input into a shell until it runs out, returning everything written to the console.
+    fn run(input: &str) -> String {
+        let mut console = Buffer::new(input);
+        {
+            let mut shell = Shell::new(&mut console);
+            while shell.read_and_execute() {}
+        }
+        String::from(console.output())
+    }
+
+    #[test]
+    fn splits_words() {
+        assert_eq!(words("echo  a   b "), ["echo", "a", "b"]);
+        assert_eq!(words("echo 'a  b' \"c d\" e\\ f '' \"\""), ["echo", "a  b", "c d", "e f", "", ""]);
+        assert_eq!(words("echo \"a\"'b'c"), ["echo", "abc"]);
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(words("echo \"$N y\" '$N' \\$N"), ["echo", "42 y", "$N", "$N"]);
+        assert_eq!(words("echo \\x41\\t\\\\ \\'"), ["echo", "A\t\\", "'"]);
+        assert_eq!(words("echo \\x7f"), ["echo", "\x7f"]);
+    }
+
+    #[test]
+    fn splits_commands() {
+        let commands = commands("a; b && c || d &").unwrap();
+        let separators: Vec<Option<Separator>> = commands.iter().map(|command| command.1).collect();
+        assert_eq!(separators, [Some(Separator::Sequence), Some(Separator::And), Some(Separator::Or), Some(Separator::Background), None]);
+        assert_eq!(commands[2].0, ["c"]);
+        // Only a `&` that ends a command runs it in the background.
+        assert_eq!(words("eval 6 & 3 | 1"), ["eval", "6", "&", "3", "|", "1"]);
+        assert_eq!(words("echo ';' \"&&\""), ["echo", ";", "&&"]);
+    }
+
+    #[test]
+    fn reads_the_whole_line_before_running_it() {
+        let output = run("echo first; echo 'oops\r");
+        assert!(output.contains("Error: unclosed string found"));
+        assert!(!output.contains("first \n"));
+        // $? is still expanded as each command runs.
+        assert!(run("nope; echo $?\r").contains("Unknown command: 'nope'\n127 \n"));
+    }
+
+    #[test]
+    fn reports_read_errors() {
+        assert_eq!(commands("echo 'a").unwrap_err(), "unclosed string found");
+        assert_eq!(commands("echo \"a").unwrap_err(), "unclosed string found");
+        assert_eq!(commands("echo ${N").unwrap_err(), "unclosed ${ found");
+        assert_eq!(commands("echo \\q").unwrap_err(), "unknown escape sequence \\q");
+        assert_eq!(commands("echo \\x4").unwrap_err(), "\\x must be followed by two hex digits from 00 to 7f");
+        assert_eq!(commands("echo \\xff").unwrap_err(), "\\x must be followed by two hex digits from 00 to 7f");
+        assert_eq!(commands("echo \\").unwrap_err(), "line ends with a backslash");
+    }
+
+    #[test]
+    fn expands_variables() {
+        assert_eq!(words("echo $N ${N}x $MISSING $ $N$N"), ["echo", "42", "42x", "$", "4242"]);
+    }
+
+    #[test]
+    fn sets_environment_variables() {
+        let output = run("set A hello  world\recho $A\rset PROMPT x\rset 1A x\runset A\recho [$A]\renv\r");
+        assert!(output.contains("echo $A\nhello world \n"));
+        assert!(output.contains("Can't set PROMPT: variable is read-only"));
+        assert!(output.contains("Can't set 1A: names must start with a letter"));
+        assert!(output.contains("echo [$A]\n[] \n"));
+        assert!(output.contains("PROMPT= > \nUPTIME=00:00:00\n"));
+    }
+
+    #[test]
+    fn runs_lines_until_input_ends() {
+        let output = run("echo a; nope || echo b\reval 1/0 && echo c\recho $?\r");
+        assert!(output.contains("a \nUnknown command: 'nope'\nb \n"));
+        assert!(!output.contains("c \n"));
+        assert!(output.ends_with(" > echo $?\n1 \n > \n"));
+    }
+
+    #[test]
+    fn shows_the_status_in_the_prompt() {
+        let output = run("nope\rstatus\rstatus prompt on\rnope\rstatus\rstatus prompt off\rnope\recho $?\r");
+        assert!(output.contains(" > status\n127\n"));
+        assert!(output.contains("[127] > status\n127\n > status prompt off\n"));
+        assert!(output.ends_with("\n > echo $?\n127 \n > \n"));
+        assert!(run("status prompt\r").contains("Usage: status [prompt on|off]"));
+    }
+
+    #[test]
+    fn records_and_runs_scripts() {
+        let output = run("script new hi\recho hi\rnope\r.\rscript list\rscript show hi\rstatus prompt on\rrun hi\r");
+        assert!(output.contains(" | echo hi\n | nope\n | .\n"));
+        assert!(output.ends_with(" > run hi\nhi \nUnknown command: 'nope'\n[127] > \n"));
+        assert!(output.contains(" > script list\nhi (2 lines)\n"));
+        assert!(output.contains(" > script show hi\necho hi\nnope\n"));
+        assert!(run("run missing\r").contains("No such script: 'missing'"));
+        assert!(run("script new 1x\r").contains("Invalid script name: '1x'"));
+        // Ctrl-C throws the script away.
+        assert!(!run("script new hi\recho hi\r\x03script list\r").contains("hi (1 lines)"));
+    }
+
+    #[test]
+    fn ctrl_c_stops_a_script() {
+        let output = run("script new s\recho a\recho b\r.\rrun s\r\x03echo $?\r");
+        assert!(output.contains(" > run s\na \n^C\n > echo $?\n130 \n"));
+        // A line that was interrupted stops the script as well.
+        let output = run("script new s\rwatch -n 10 echo w\recho after\r.\rrun s\r\x03echo $?\r");
+        assert!(!output.contains("after \n"));
+        assert!(output.contains(" > echo $?\n130 \n"));
+    }
+
+    #[test]
+    fn limits_how_deeply_scripts_run() {
+        let output = run("script new again\rrun again\r.\rrun again\r");
+        assert_eq!(output.matches("Can't run 'again', scripts are nested too deeply").count(), 1);
+    }
+
+    #[test]
+    fn expands_aliases() {
+        let output = run("alias hi='echo hi' again=hi\ragain there\ralias hi\runalias hi\rhi\r");
+        assert!(output.contains(" > again there\nhi there \n"));
+        assert!(output.contains(" > alias hi\nalias hi='echo hi'\n"));
+        assert!(output.contains("Unknown command: 'hi'"));
+        assert!(run("alias 1x=echo\r").contains("Invalid alias name: '1x'"));
+        assert!(run("alias two='echo a; echo b'\rtwo\r").contains("Error: aliases can only contain one command"));
+    }
+
+    #[test]
+    fn aliases_can_shadow_commands() {
+        let output = run("alias echo='echo hi'\recho there\r");
+        assert!(output.contains(" > echo there\nhi there \n"));
+    }
+
+    #[test]
+    fn detects_alias_loops() {
+        let output = run("alias a=b b=a\ra\recho $?\r");
+        assert!(output.contains(" > a\nError: alias expands back to itself\n > echo $?\n1 \n"));
+    }
+
+    #[test]
+    fn watch_stops_on_a_key() {
+        let output = run("watch -n 10 echo hi\rqecho $?\r");
+        assert_eq!(output.matches("Every 10ms, press any key to stop\nhi \n").count(), 1);
+        assert!(output.contains(" > echo $?\n0 \n"));
+        assert!(run("watch -n 10 nope\r\x03echo $?\r").contains(" > echo $?\n130 \n"));
+        assert!(run("watch -n 0 echo hi\r").contains("-n expects a number of milliseconds"));
+    }
+
+    #[test]
+    fn watch_stops_when_the_console_closes() {
+        let output = run("status prompt on\rwatch -n 10 echo hi\r");
+        assert_eq!(output.matches("Every 10ms").count(), 1);
+        assert!(output.ends_with("[130] > \n"));
+    }
+
+    fn greet(shell: &mut Shell, args: &[&str]) -> Status {
+        let _ = writeln!(shell.console(), "hello {}", args.len());
+        SUCCESS
+    }
+
+    static GREET: Command = Command { name: "greet", usage: "greet", help: "", handler: greet, complete: None };
+
+    #[test]
+    fn registered_commands_write_to_the_console() {
+        register(&GREET).unwrap();
+        assert!(run("greet a b\r").contains("greet a b\nhello 2\n"));
+    }
+
+    #[test]
+    fn stops_on_ctrl_d() {
+        let output = run("echo a\r\x04echo b\r");
+        assert!(output.contains("a \n"));
+        assert!(!output.contains("b \n"));
+    }
+}
@@ -0,0 +1,27 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Named scripts typed in with `script new` and played back with `run`. Scripts are only kept in
+// RAM, so they are lost on reset.
+
+use kernel::collections::{Vec, String};
+use super::table::Table;
+
+pub const MAX_DEPTH: usize = 4;
+
+/// The lines of each script, by name.
+pub type Scripts = Table<Vec<String>>;
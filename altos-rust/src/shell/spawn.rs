@@ -0,0 +1,96 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Task functions that can be started from the shell with `spawn`. Like commands, other modules can
+// add their own with `register_task` before the shell task starts.
+
+use kernel::task::Priority;
+use kernel::task::args::Args;
+use kernel::sync::Mutex;
+use kernel::collections::Vec;
+use super::RegisterError;
+use super::board::{blink_task, BLINK_STACK_SIZE};
+
+/// The maximum number of task functions that can be registered in addition to the builtins.
+pub const MAX_SPAWNABLE: usize = 8;
+
+static REGISTRY: Mutex<[Option<&'static Spawnable>; MAX_SPAWNABLE]> = Mutex::new([None; MAX_SPAWNABLE]);
+
+static BUILTIN_TASKS: [Spawnable; 1] = [
+    Spawnable { name: "blink", code: blink_task, stack_size: BLINK_STACK_SIZE, priority: Priority::Low },
+];
+
+/// A task function that can be started from the shell.
+///
+/// Arguments given to `spawn` are parsed as numbers and added to the task's `Args` in the order
+/// they were given.
+pub struct Spawnable {
+    /// The name used to spawn the task, also used as the task's name.
+    pub name: &'static str,
+    /// The task function.
+    pub code: fn(&mut Args),
+    /// The stack size used if `--stack` is not given.
+    pub stack_size: usize,
+    /// The priority used if `--prio` is not given.
+    pub priority: Priority,
+}
+
+/// Allow a task function to be started with `spawn`.
+///
+/// Task functions should be registered before the shell task is started.
+pub fn register_task(task: &'static Spawnable) -> Result<(), RegisterError> {
+    if find(task.name).is_some() {
+        return Err(RegisterError::Duplicate);
+    }
+    let mut registry = REGISTRY.lock();
+    for slot in registry.iter_mut() {
+        if slot.is_none() {
+            *slot = Some(task);
+            return Ok(());
+        }
+    }
+    Err(RegisterError::Full)
+}
+
+/// Look up a task function by name.
+pub fn find(name: &str) -> Option<&'static Spawnable> {
+    if let Some(task) = BUILTIN_TASKS.iter().find(|task| task.name == name) {
+        return Some(task);
+    }
+    let registry = REGISTRY.lock();
+    registry.iter()
+        .filter_map(|slot| *slot)
+        .find(|task| task.name == name)
+}
+
+/// All task functions that can be spawned.
+pub fn spawnable() -> Vec<&'static Spawnable> {
+    let mut tasks: Vec<&'static Spawnable> = BUILTIN_TASKS.iter().collect();
+    let registry = REGISTRY.lock();
+    tasks.extend(registry.iter().filter_map(|slot| *slot));
+    tasks
+}
+
+/// Parse a priority as given to `--prio`.
+pub fn parse_priority(string: &str) -> Option<Priority> {
+    match string {
+        "critical" => Some(Priority::Critical),
+        "normal" => Some(Priority::Normal),
+        "low" => Some(Priority::Low),
+        _ => None,
+    }
+}
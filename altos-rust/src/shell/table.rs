@@ -0,0 +1,65 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// The name to value lookup shared by aliases, variables and scripts.
+
+use kernel::collections::{Vec, String};
+use core::slice;
+
+/// Values looked up by name, kept in the order they were first set.
+///
+/// The shell only ever holds a handful of these, so searching a `Vec` is all it needs.
+pub struct Table<T> {
+    entries: Vec<(String, T)>,
+}
+
+impl<T> Table<T> {
+    pub fn new() -> Self {
+        Table {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.iter().find(|entry| entry.0 == name).map(|entry| &entry.1)
+    }
+
+    /// Set the value for a name, replacing any value it already had.
+    pub fn set(&mut self, name: &str, value: T) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.0 == name) {
+            entry.1 = value;
+            return;
+        }
+        self.entries.push((String::from(name), value));
+    }
+
+    /// Remove a name, returning false if it wasn't set.
+    pub fn remove(&mut self, name: &str) -> bool {
+        match self.entries.iter().position(|entry| entry.0 == name) {
+            Some(index) => {
+                self.entries.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The names and their values in the order they were first set.
+    pub fn iter(&self) -> slice::Iter<(String, T)> {
+        self.entries.iter()
+    }
+}
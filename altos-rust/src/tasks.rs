@@ -0,0 +1,260 @@
+/*
+ * Copyright (C) 2017 AltOS-Rust Team
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+// Keeps track of the tasks created by the application so they can be inspected from the shell.
+// Tasks should be created with `tasks::new_task` rather than `kernel::syscall::new_task` directly,
+// otherwise they won't show up here.
+//
+// Each task's stack is painted with a known pattern when it starts, so the most stack the task has
+// used can be measured by finding how much of the pattern is left. Only tracked tasks are painted,
+// so the kernel's own tasks, tasks created with `kernel::syscall::new_task` and tasks created
+// after `MAX_TASKS` were already tracked can't be measured.
+
+use cortex_m0::kernel;
+use kernel::task::{TaskHandle, Priority};
+use kernel::task::args::Args;
+use kernel::collections::Vec;
+use kernel::sync::Mutex;
+use core::cmp;
+use core::ptr;
+
+/// The maximum number of tasks that can be tracked at once.
+pub const MAX_TASKS: usize = 8;
+
+/// The word written over the unused part of each stack.
+pub const STACK_PAINT: u32 = 0xDEAD_BEEF;
+
+/// The smallest stack a task should be given. The kernel's initial frame and the frames of the
+/// entry points in here take part of it, and what is left has to be enough for the task to call
+/// into the kernel.
+pub const MIN_STACK_SIZE: usize = 512;
+
+// The xPSR the kernel starts every task with, which only has the Thumb bit set.
+const INITIAL_XPSR: usize = 0x0100_0000;
+
+// The words the processor pops from the stack when the kernel first switches to a task: r0-r3,
+// r12, lr, pc and xPSR.
+const EXCEPTION_FRAME_WORDS: usize = 8;
+
+// Bytes left unpainted at the bottom of each stack. The kernel may leave a gap above a task's
+// first frame to keep the stack pointer 8-byte aligned, which would put the real top of the stack
+// up to this far above the one found by `find_stack_top`.
+const STACK_ALIGN_SLACK: usize = 8;
+
+// Symbols defined by cortex_m0.ld. Every task's stack is allocated from the heap.
+extern "C" {
+    static _heap_end: u8;
+}
+
+static TASKS: Mutex<[Option<TaskInfo>; MAX_TASKS]> = Mutex::new([None; MAX_TASKS]);
+
+// The lowest painted address of the stack of the task in each slot of `TASKS`, 0 if the stack
+// hasn't been painted.
+static STACKS: Mutex<[usize; MAX_TASKS]> = Mutex::new([0; MAX_TASKS]);
+
+// How many tasks have been created while every slot in `TASKS` was in use.
+static UNTRACKED: Mutex<usize> = Mutex::new(0);
+
+// The task waiting to start in each slot of `TASKS`, taken by `start_task` when it runs.
+static STARTS: Mutex<[Option<Start>; MAX_TASKS]> = Mutex::new([None; MAX_TASKS]);
+
+// Where tasks in each slot start. Task arguments are passed on to the task untouched, so the only
+// way to tell `start_task` which slot it is in is a separate entry point for each one.
+const ENTRIES: [fn(&mut Args); MAX_TASKS] = [
+    start_task_0, start_task_1, start_task_2, start_task_3,
+    start_task_4, start_task_5, start_task_6, start_task_7,
+];
+
+/// Information about a task recorded when it was created.
+#[derive(Copy, Clone)]
+pub struct TaskInfo {
+    pub handle: TaskHandle,
+    pub name: &'static str,
+    pub priority: Priority,
+    pub stack_size: usize,
+    slot: usize,
+}
+
+impl TaskInfo {
+    /// Returns true if the task has not exited or been destroyed.
+    pub fn is_alive(&self) -> bool {
+        self.handle.tid().is_ok()
+    }
+
+    /// The most stack the task has used so far in bytes, or `None` if it can't be measured.
+    ///
+    /// The paint stops a little short of the real bottom of the stack, so this can overestimate
+    /// slightly but never underestimates. Stacks are only painted once the top of the stack has
+    /// been found, see `find_stack_top`, otherwise this is always `None`.
+    pub fn stack_used(&self) -> Option<usize> {
+        if !self.is_alive() {
+            return None;
+        }
+        let bottom = STACKS.lock()[self.slot];
+        if bottom == 0 {
+            return None;
+        }
+        let top = bottom + self.stack_size;
+        let mut addr = bottom;
+        while addr < top && unsafe { ptr::read_volatile(addr as *const u32) } == STACK_PAINT {
+            addr += 4;
+        }
+        Some(self.stack_size - (addr - bottom))
+    }
+}
+
+// What `start_task` needs to start the real task.
+#[derive(Copy, Clone)]
+struct Start {
+    code: fn(&mut Args),
+    stack_size: usize,
+}
+
+/// Create a new task and keep track of it.
+///
+/// Takes the same arguments as `kernel::syscall::new_task`. If too many tasks are being tracked the
+/// task is still created, it just won't be listed or have its stack measured, and is counted by
+/// `untracked` instead.
+pub fn new_task(code: fn(&mut Args), args: Args, stack_size: usize, priority: Priority, name: &'static str) -> TaskHandle {
+    // A slot is in use until its task has started and then died.
+    let slot = {
+        let tasks = TASKS.lock();
+        let mut starts = STARTS.lock();
+        let slot = (0..MAX_TASKS).find(|&slot| {
+            starts[slot].is_none() && tasks[slot].map_or(true, |task| !task.is_alive())
+        });
+        if let Some(slot) = slot {
+            starts[slot] = Some(Start { code: code, stack_size: stack_size });
+        }
+        slot
+    };
+
+    let slot = match slot {
+        Some(slot) => slot,
+        None => {
+            *UNTRACKED.lock() += 1;
+            return kernel::syscall::new_task(code, args, stack_size, priority, name);
+        },
+    };
+    STACKS.lock()[slot] = 0;
+    let handle = kernel::syscall::new_task(ENTRIES[slot], args, stack_size, priority, name);
+    TASKS.lock()[slot] = Some(TaskInfo {
+        handle: handle,
+        name: name,
+        priority: priority,
+        stack_size: stack_size,
+        slot: slot,
+    });
+    handle
+}
+
+fn start_task_0(args: &mut Args) { start_task(0, args) }
+fn start_task_1(args: &mut Args) { start_task(1, args) }
+fn start_task_2(args: &mut Args) { start_task(2, args) }
+fn start_task_3(args: &mut Args) { start_task(3, args) }
+fn start_task_4(args: &mut Args) { start_task(4, args) }
+fn start_task_5(args: &mut Args) { start_task(5, args) }
+fn start_task_6(args: &mut Args) { start_task(6, args) }
+fn start_task_7(args: &mut Args) { start_task(7, args) }
+
+// Every task created with `new_task` in a slot starts here. Paint the unused part of the stack,
+// then run the real task function.
+fn start_task(slot: usize, args: &mut Args) {
+    let start = match STARTS.lock()[slot].take() {
+        Some(start) => start,
+        None => unreachable!(),
+    };
+    // Somewhere in this frame, which is below the kernel's first frame for the task.
+    let here = 0usize;
+    let top = find_stack_top(ENTRIES[slot], &here as *const usize as usize, start.stack_size);
+    if let Some(top) = top {
+        if top >= start.stack_size + STACK_ALIGN_SLACK {
+            let bottom = top - start.stack_size + STACK_ALIGN_SLACK;
+            paint_stack(bottom);
+            STACKS.lock()[slot] = bottom;
+        }
+    }
+    (start.code)(args);
+}
+
+// Find the top of the stack of a task that started at `entry` by looking for the frame the kernel
+// built at the top of it, from `from` up. The processor has popped it by now, but nothing has
+// written over it since: it is above everything the task has pushed.
+//
+// The frame is 8 words ending with the initial xPSR, and the kernel starts the task at `entry`, so
+// that has to be one of them too. If it isn't found the stack isn't painted rather than guessing.
+// Never inlined, so its own locals are below `from` where they can't be mistaken for the frame.
+#[inline(never)]
+fn find_stack_top(entry: fn(&mut Args), from: usize, stack_size: usize) -> Option<usize> {
+    let entry = entry as usize;
+    let end = cmp::min(from.saturating_add(stack_size), unsafe { &_heap_end as *const u8 as usize });
+    let first = (from + 3) & !3;
+    let mut addr = first + 4 * (EXCEPTION_FRAME_WORDS - 1);
+    while addr + 4 <= end {
+        if unsafe { ptr::read_volatile(addr as *const usize) } == INITIAL_XPSR {
+            let frame = addr - 4 * (EXCEPTION_FRAME_WORDS - 1);
+            let starts_task = (0..EXCEPTION_FRAME_WORDS - 1).any(|i| {
+                let word = unsafe { ptr::read_volatile((frame + 4 * i) as *const usize) };
+                word | 1 == entry | 1
+            });
+            if starts_task {
+                return Some(addr + 4);
+            }
+        }
+        addr += 4;
+    }
+    None
+}
+
+// Paint from `bottom` up to the stack pointer with `STACK_PAINT`. Nothing below the stack pointer
+// is in use, but the compiler can put locals anywhere in a frame, so the whole thing is done in
+// assembly with only registers. `bottom` must be a multiple of 4.
+#[inline(never)]
+fn paint_stack(bottom: usize) {
+    unsafe {
+        asm!("
+            mov r0, sp
+            mov r4, r1
+        1:
+            cmp r4, r0
+            bhs 2f
+            str r3, [r4]
+            adds r4, #4
+            b 1b
+        2:
+            "
+            :
+            : "{r1}"(bottom), "{r3}"(STACK_PAINT)
+            : "r0", "r4", "cc", "memory"
+            : "volatile");
+    }
+}
+
+/// How many tasks were created without being tracked because `MAX_TASKS` tasks already were. They
+/// don't show up in `tasks`, and some of them may have exited since.
+pub fn untracked() -> usize {
+    *UNTRACKED.lock()
+}
+
+/// All of the tracked tasks that are still alive.
+pub fn tasks() -> Vec<TaskInfo> {
+    let tasks = TASKS.lock();
+    tasks.iter()
+        .filter_map(|slot| *slot)
+        .filter(|task| task.is_alive())
+        .collect()
+}